@@ -0,0 +1,210 @@
+use std::{
+    cell::{Cell, RefCell},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use reqwest::{blocking::Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{error::ClientError, redact::redact_in_place};
+
+/// Directory of recorded request/response pairs, one JSON file per pair
+pub(crate) enum Cassette {
+    /// Every sent request is saved with its response
+    Record(Recorder),
+    /// Responses are served from files, nothing is sent
+    Replay(Player),
+}
+
+impl Cassette {
+    pub(crate) fn record(dir: &Path) -> Result<Self, ClientError> {
+        fs::create_dir_all(dir).map_err(|e| invalid_dir(dir, e))?;
+        // Recording to existing cassette continues it
+        let last = interaction_files(dir)?
+            .iter()
+            .filter_map(|p| p.file_name()?.to_str()?.get(..4)?.parse::<usize>().ok())
+            .max()
+            .unwrap_or(0);
+
+        Ok(Cassette::Record(Recorder {
+            dir: dir.to_path_buf(),
+            next: Cell::new(last + 1),
+        }))
+    }
+
+    pub(crate) fn replay(dir: &Path) -> Result<Self, ClientError> {
+        let mut interactions = Vec::new();
+        for path in interaction_files(dir)? {
+            let text = fs::read_to_string(&path).map_err(|e| invalid_dir(&path, e))?;
+            let interaction: Interaction =
+                serde_json::from_str(&text).map_err(|e| invalid_dir(&path, e))?;
+            interactions.push(interaction);
+        }
+
+        Ok(Cassette::Replay(Player {
+            dir: dir.to_path_buf(),
+            used: RefCell::new(vec![false; interactions.len()]),
+            interactions,
+        }))
+    }
+}
+
+pub(crate) struct Recorder {
+    dir: PathBuf,
+    next: Cell<usize>,
+}
+
+impl Recorder {
+    pub(crate) fn save(
+        &self,
+        request: RecordedRequest,
+        status: StatusCode,
+        body: &[u8],
+    ) -> Result<(), ClientError> {
+        let (body, text) = match serde_json::from_slice::<Value>(body) {
+            Ok(mut json) => {
+                redact_in_place(&mut json);
+                (Some(json), None)
+            }
+            Err(_) => (None, Some(String::from_utf8_lossy(body).into_owned())),
+        };
+
+        let nmb = self.next.get();
+        let path = self.dir.join(format!(
+            "{:04}-{}-{}.json",
+            nmb,
+            request.method.to_lowercase(),
+            slug(&request.path)
+        ));
+        let interaction = Interaction {
+            request,
+            response: RecordedResponse {
+                status: status.as_u16(),
+                body,
+                text,
+            },
+        };
+
+        let json = serde_json::to_string_pretty(&interaction)
+            .map_err(|e| ClientError::Cassette(e.to_string()))?;
+        fs::write(&path, json + "\n").map_err(|e| {
+            ClientError::Cassette(format!("Failed to write {}: {}", path.display(), e))
+        })?;
+        self.next.set(nmb + 1);
+        Ok(())
+    }
+}
+
+pub(crate) struct Player {
+    dir: PathBuf,
+    interactions: Vec<Interaction>,
+    used: RefCell<Vec<bool>>,
+}
+
+impl Player {
+    /// Returns response of the first unused interaction with equal request
+    pub(crate) fn play(
+        &self,
+        request: &RecordedRequest,
+    ) -> Result<(StatusCode, Vec<u8>), ClientError> {
+        let mut used = self.used.borrow_mut();
+        let found = self
+            .interactions
+            .iter()
+            .zip(used.iter())
+            .position(|(interaction, done)| !done && interaction.request == *request);
+
+        let i = found.ok_or_else(|| {
+            ClientError::Cassette(format!(
+                "No recorded response to {} {} in {}",
+                request.method,
+                request.path,
+                self.dir.display()
+            ))
+        })?;
+        used[i] = true;
+
+        let response = &self.interactions[i].response;
+        let status = StatusCode::from_u16(response.status)
+            .map_err(|e| ClientError::Cassette(format!("Invalid recorded status: {}", e)))?;
+        let body = match (&response.body, &response.text) {
+            (Some(json), _) => json.to_string().into_bytes(),
+            (None, Some(text)) => text.clone().into_bytes(),
+            (None, None) => Vec::new(),
+        };
+        Ok((status, body))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+/// Request without headers, so api key is never recorded. Path is relative to
+/// base URL, so cassette can be replayed against any server.
+#[derive(Serialize, Deserialize, PartialEq)]
+pub(crate) struct RecordedRequest {
+    method: String,
+    /// Path with query
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+}
+
+impl RecordedRequest {
+    pub(crate) fn new(request: &Request, base_url: &str) -> Self {
+        let url = request.url().as_str();
+        let mut body: Option<Value> = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .and_then(|b| serde_json::from_slice(b).ok());
+        if let Some(body) = &mut body {
+            redact_in_place(body);
+        }
+
+        RecordedRequest {
+            method: request.method().to_string(),
+            path: url.strip_prefix(base_url).unwrap_or(url).to_string(),
+            body,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    /// JSON body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+    /// Body which is not JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+/// Sorted JSON files of cassette directory
+fn interaction_files(dir: &Path) -> Result<Vec<PathBuf>, ClientError> {
+    let mut files = fs::read_dir(dir)
+        .map_err(|e| invalid_dir(dir, e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn invalid_dir(path: &Path, e: impl std::fmt::Display) -> ClientError {
+    ClientError::InvalidConfig(format!("Invalid cassette {}: {}", path.display(), e))
+}
+
+/// Part of file name describing request path, e.g. `user-3-keys`
+fn slug(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    path.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
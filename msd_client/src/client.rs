@@ -0,0 +1,595 @@
+use std::{
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    blocking::{Client, Request, RequestBuilder, Response},
+    header, Certificate, Identity, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    cassette::{Cassette, RecordedRequest},
+    error::ClientError,
+    models::*,
+    retry::RetryPolicy,
+    trace::{TraceEvent, TracedResponse},
+};
+
+/// Header with api key
+pub static API_KEY_HEADER: &str = "x-api-key";
+
+static DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+
+type RequestInspector = Box<dyn Fn(&PreparedRequest)>;
+type Tracer = Box<dyn Fn(&TraceEvent)>;
+
+/// Builder of [`MsdClient`]
+pub struct MsdClientBuilder {
+    base_url: String,
+    api_key: Option<String>,
+    user_agent: String,
+    connect_timeout: Duration,
+    timeout: Option<Duration>,
+    request_inspector: Option<RequestInspector>,
+    tracer: Option<Tracer>,
+    ca_certs: Option<Vec<u8>>,
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+    accept_invalid_certs: bool,
+    retry: RetryPolicy,
+    dry_run: bool,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+impl MsdClientBuilder {
+    /// Api key sent in `x-api-key` header
+    pub fn api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Connect timeout. 5 seconds by default.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Total request timeout. No timeout by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retries of failed requests. [`RetryPolicy::default`] by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Callback called with every request before it is sent, also in dry-run mode
+    pub fn inspect_requests(mut self, inspector: impl Fn(&PreparedRequest) + 'static) -> Self {
+        self.request_inspector = Some(Box::new(inspector));
+        self
+    }
+
+    /// Callback called with every attempt of request, its response and retries
+    pub fn trace(mut self, tracer: impl Fn(&TraceEvent) + 'static) -> Self {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
+
+    /// PEM bundle of CA certificates trusted in addition to system ones
+    pub fn ca_certs_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca_certs = Some(pem.into());
+        self
+    }
+
+    /// PEM certificate chain and PKCS#8 PEM key used for mutual TLS
+    pub fn client_identity_pem(
+        mut self,
+        cert: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.identity = Some((cert.into(), key.into()));
+        self
+    }
+
+    /// Disables verification of server certificates and host names.
+    /// Anyone on the network path can read and change the traffic.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Requests changing data are not sent but returned as
+    /// [`Outcome::Prepared`]. Reading requests are sent as usual.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Saves every request and its response to directory `dir`, one JSON file
+    /// per request. Api key and secret fields of bodies are not saved.
+    pub fn record(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.record = Some(dir.into());
+        self
+    }
+
+    /// Serves responses recorded by [`MsdClientBuilder::record`] in `dir`
+    /// instead of sending requests. Request without recorded response fails
+    /// with [`ClientError::Cassette`].
+    pub fn replay(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.replay = Some(dir.into());
+        self
+    }
+
+    pub fn build(self) -> Result<MsdClient, ClientError> {
+        let mut builder = Client::builder().connect_timeout(self.connect_timeout);
+
+        let mut headers = header::HeaderMap::new();
+        let user_agent = header::HeaderValue::from_str(&self.user_agent).map_err(|_| {
+            ClientError::InvalidConfig("User agent contains invalid characters".to_string())
+        })?;
+        headers.insert(header::USER_AGENT, user_agent);
+        if let Some(k) = &self.api_key {
+            let value = header::HeaderValue::from_str(k).map_err(|_| {
+                ClientError::InvalidConfig("Api key contains invalid characters".to_string())
+            })?;
+            headers.insert(API_KEY_HEADER, value);
+        }
+        builder = builder.default_headers(headers.clone());
+
+        if let Some(t) = self.timeout {
+            builder = builder.timeout(t);
+        }
+
+        if let Some(pem) = &self.ca_certs {
+            let certs = Certificate::from_pem_bundle(pem)
+                .map_err(|e| ClientError::InvalidConfig(format!("Invalid CA bundle: {}", e)))?;
+            if certs.is_empty() {
+                return Err(ClientError::InvalidConfig(
+                    "CA bundle contains no certificates".to_string(),
+                ));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if let Some((cert, key)) = &self.identity {
+            let identity = Identity::from_pkcs8_pem(cert, key).map_err(|e| {
+                ClientError::InvalidConfig(format!("Invalid client certificate or key: {}", e))
+            })?;
+            builder = builder.identity(identity);
+        }
+
+        if self.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        let http = builder
+            .build()
+            .map_err(|e| ClientError::InvalidConfig(e.to_string()))?;
+
+        let cassette = match (&self.record, &self.replay) {
+            (Some(_), Some(_)) => {
+                return Err(ClientError::InvalidConfig(
+                    "Cassette can not be recorded and replayed at once".to_string(),
+                ))
+            }
+            (Some(dir), None) => Some(Cassette::record(dir)?),
+            (None, Some(dir)) => Some(Cassette::replay(dir)?),
+            (None, None) => None,
+        };
+
+        Ok(MsdClient {
+            http,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            request_inspector: self.request_inspector,
+            tracer: self.tracer,
+            retry: self.retry,
+            default_headers: headers,
+            dry_run: self.dry_run,
+            cassette,
+        })
+    }
+}
+
+/// Client of MSD server REST API
+pub struct MsdClient {
+    http: Client,
+    base_url: String,
+    request_inspector: Option<RequestInspector>,
+    tracer: Option<Tracer>,
+    retry: RetryPolicy,
+    /// Headers added to every request, shown to request inspector
+    default_headers: header::HeaderMap,
+    dry_run: bool,
+    cassette: Option<Cassette>,
+}
+
+impl MsdClient {
+    /// Creates builder of client for API at `base_url`, e.g. `http://127.0.0.1:8000/api/v1`
+    pub fn builder(base_url: impl Into<String>) -> MsdClientBuilder {
+        MsdClientBuilder {
+            base_url: base_url.into(),
+            api_key: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            connect_timeout: Duration::from_secs(5),
+            timeout: None,
+            request_inspector: None,
+            tracer: None,
+            ca_certs: None,
+            identity: None,
+            accept_invalid_certs: false,
+            retry: RetryPolicy::default(),
+            dry_run: false,
+            record: None,
+            replay: None,
+        }
+    }
+
+    /// Creates client with default settings
+    pub fn new(base_url: impl Into<String>) -> Result<Self, ClientError> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Sends request, retrying it by policy, and checks server error envelope
+    fn send(&self, request: RequestBuilder) -> Result<Value, ClientError> {
+        let request = self.build(request)?;
+        self.exchange(request)
+    }
+
+    /// Sends request changing data on server. In dry-run mode it is returned
+    /// prepared instead.
+    fn submit(&self, request: RequestBuilder) -> Result<Outcome<Value>, ClientError> {
+        let request = self.build(request)?;
+        if self.dry_run {
+            return Ok(Outcome::Prepared(prepared(&request, &self.default_headers)));
+        }
+        self.exchange(request).map(Outcome::Sent)
+    }
+
+    fn build(&self, request: RequestBuilder) -> Result<Request, ClientError> {
+        let request = request.build()?;
+        if let Some(inspector) = &self.request_inspector {
+            inspector(&prepared(&request, &self.default_headers));
+        }
+        Ok(request)
+    }
+
+    fn exchange(&self, request: Request) -> Result<Value, ClientError> {
+        let (status, body) = match &self.cassette {
+            Some(Cassette::Replay(player)) => {
+                player.play(&RecordedRequest::new(&request, &self.base_url))?
+            }
+            Some(Cassette::Record(recorder)) => {
+                let recorded = RecordedRequest::new(&request, &self.base_url);
+                let (status, body) = self.fetch(request)?;
+                recorder.save(recorded, status, &body)?;
+                (status, body)
+            }
+            None => self.fetch(request)?,
+        };
+
+        let json_value = match serde_json::from_slice::<Value>(&body) {
+            Ok(v) => v,
+            Err(_) if !status.is_success() => return Err(ClientError::HttpStatus(status)),
+            Err(e) => {
+                return Err(ClientError::MalformedResponse(format!(
+                    "invalid JSON: {}",
+                    e
+                )))
+            }
+        };
+
+        check_server_error(&json_value, status)?;
+
+        Ok(json_value)
+    }
+
+    /// Sends request, retrying it by policy, and reads body of the last response
+    fn fetch(&self, request: Request) -> reqwest::Result<(StatusCode, Vec<u8>)> {
+        let retryable = self.retry.allows(request.method());
+
+        let mut attempt = 0;
+        loop {
+            let started = Instant::now();
+            let result = match request.try_clone() {
+                Some(r) => self.execute(r, attempt),
+                None => return self.read(attempt, started, self.execute(request, attempt)),
+            };
+
+            match self.retry.delay(attempt, &result).filter(|_| retryable) {
+                Some(delay) => {
+                    // Response of failed attempt is only traced
+                    let _ = self.read(attempt, started, result);
+                    self.trace(|| TraceEvent::Retry { attempt, delay });
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                None => return self.read(attempt, started, result),
+            }
+        }
+    }
+
+    fn trace(&self, event: impl FnOnce() -> TraceEvent) {
+        if let Some(tracer) = &self.tracer {
+            tracer(&event());
+        }
+    }
+
+    /// Sends one attempt of request and receives response headers
+    fn execute(&self, request: Request, attempt: u32) -> reqwest::Result<Response> {
+        self.trace(|| TraceEvent::Request {
+            attempt,
+            request: prepared(&request, &self.default_headers),
+        });
+        self.http.execute(request)
+    }
+
+    /// Reads body of response to attempt sent at `started`
+    fn read(
+        &self,
+        attempt: u32,
+        started: Instant,
+        result: reqwest::Result<Response>,
+    ) -> reqwest::Result<(StatusCode, Vec<u8>)> {
+        let headers_time = started.elapsed();
+        let result = result.and_then(|response| {
+            let status = response.status();
+            let headers = header_list(response.headers());
+            let body = response.bytes()?;
+            Ok((status, headers, body.to_vec()))
+        });
+
+        match &result {
+            Ok((status, headers, body)) => self.trace(|| TraceEvent::Response {
+                attempt,
+                response: TracedResponse {
+                    status: status.as_u16(),
+                    headers: headers.clone(),
+                    body: String::from_utf8_lossy(body).into_owned(),
+                    headers_time,
+                    total_time: started.elapsed(),
+                },
+            }),
+            Err(e) => self.trace(|| TraceEvent::Failed {
+                attempt,
+                error: e.to_string(),
+                elapsed: started.elapsed(),
+            }),
+        }
+        result.map(|(status, _, body)| (status, body))
+    }
+
+    pub fn create_user(&self, user: &NewUser) -> Result<Outcome<CreatedUser>, ClientError> {
+        self.submit(self.http.post(self.url("/user/")).json(user))?
+            .try_map(parse)
+    }
+
+    pub fn get_user(&self, id: i32) -> Result<User, ClientError> {
+        let json = self.send(self.http.get(self.url(&format!("/user/{}", id))))?;
+        parse(json)
+    }
+
+    pub fn update_user(&self, id: i32, changes: &UserChanges) -> Result<Outcome<()>, ClientError> {
+        let url = self.url(&format!("/user/{}", id));
+        Ok(self.submit(self.http.put(url).json(changes))?.map(drop))
+    }
+
+    /// Generates a new key for user and returns it
+    pub fn generate_key(&self, user_id: i32) -> Result<Outcome<String>, ClientError> {
+        let url = self.url(&format!("/user/{}/keys", user_id));
+        self.submit(self.http.post(url))?
+            .try_map(|json| parse(field(json, "key")?))
+    }
+
+    pub fn list_keys(&self, user_id: i32) -> Result<Vec<ApiKey>, ClientError> {
+        let url = self.url(&format!("/user/{}/keys", user_id));
+        let json = self.send(self.http.get(url))?;
+        parse(field(json, "keys")?)
+    }
+
+    pub fn get_key(&self, user_id: i32, nmb: usize) -> Result<ApiKey, ClientError> {
+        let url = self.url(&format!("/user/{}/keys/{}", user_id, nmb));
+        let json = self.send(self.http.get(url))?;
+        Ok(ApiKey {
+            nmb,
+            api_key: parse(field(json, "key")?)?,
+        })
+    }
+
+    pub fn revoke_key(&self, user_id: i32, nmb: usize) -> Result<Outcome<()>, ClientError> {
+        let url = self.url(&format!("/user/{}/keys/{}", user_id, nmb));
+        Ok(self.submit(self.http.delete(url))?.map(drop))
+    }
+
+    /// Creates cache and reads it back, as response to creation is only
+    /// known to carry its ID
+    pub fn create_cache(&self, cache: &NewCache) -> Result<Outcome<Cache>, ClientError> {
+        self.submit(self.http.post(self.url("/cache/")).json(cache))?
+            .try_map(|json| {
+                let created: CreatedCache = parse(json)?;
+                self.get_cache(created.id)
+            })
+    }
+
+    pub fn find_caches(&self, filter: &CacheFilter) -> Result<Vec<Cache>, ClientError> {
+        let json = self.send(self.http.get(self.url("/cache/")).query(filter))?;
+        parse(field(json, "caches")?)
+    }
+
+    /// Walks all caches matching `filter` by pages of `page_size`, starting from
+    /// `filter.offset`. `filter.limit` is ignored.
+    pub fn find_caches_paged(&self, filter: &CacheFilter, page_size: usize) -> CachePages<'_> {
+        CachePages {
+            client: self,
+            filter: CacheFilter {
+                limit: Some(page_size.max(1)),
+                offset: Some(filter.offset.unwrap_or(0)),
+                ..filter.clone()
+            },
+            done: false,
+            previous: None,
+        }
+    }
+
+    pub fn get_cache(&self, id: i32) -> Result<Cache, ClientError> {
+        let json = self.send(self.http.get(self.url(&format!("/cache/{}", id))))?;
+        parse(field(json, "caches")?)
+    }
+
+    pub fn update_cache(
+        &self,
+        id: i32,
+        changes: &CacheChanges,
+    ) -> Result<Outcome<()>, ClientError> {
+        let url = self.url(&format!("/cache/{}", id));
+        Ok(self.submit(self.http.put(url).json(changes))?.map(drop))
+    }
+
+    pub fn delete_cache(&self, id: i32) -> Result<Outcome<()>, ClientError> {
+        let url = self.url(&format!("/cache/{}", id));
+        Ok(self.submit(self.http.delete(url))?.map(drop))
+    }
+}
+
+/// Iterator over pages of [`MsdClient::find_caches_paged`]. Stops after the
+/// first error, an empty page or a page repeating the previous one. Short
+/// pages do not stop it, as server may cap the page size.
+pub struct CachePages<'a> {
+    client: &'a MsdClient,
+    filter: CacheFilter,
+    done: bool,
+    /// IDs of first and last cache of the previous page
+    previous: Option<(i32, i32)>,
+}
+
+impl Iterator for CachePages<'_> {
+    type Item = Result<Vec<Cache>, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let page = match self.client.find_caches(&self.filter) {
+            Ok(page) => page,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+
+        // Server ignoring offset repeats the same page forever
+        let bounds = match (page.first(), page.last()) {
+            (Some(first), Some(last)) => (first.id, last.id),
+            _ => {
+                self.done = true;
+                return None;
+            }
+        };
+        if self.previous == Some(bounds) {
+            self.done = true;
+            return None;
+        }
+        self.previous = Some(bounds);
+        self.filter.offset = self.filter.offset.map(|o| o + page.len());
+        Some(Ok(page))
+    }
+}
+
+/// Request as it would be sent, with default headers of client
+fn prepared(request: &Request, default_headers: &header::HeaderMap) -> PreparedRequest {
+    let mut headers = default_headers.clone();
+    headers.extend(request.headers().clone());
+
+    PreparedRequest {
+        method: request.method().to_string(),
+        url: request.url().to_string(),
+        headers: header_list(&headers),
+        body: request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .and_then(|b| serde_json::from_slice(b).ok()),
+    }
+}
+
+fn header_list(headers: &header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Extracts message from server error envelope
+fn server_error_message(json_value: &Value) -> String {
+    if let Some(msg) = json_value.get("message").and_then(Value::as_str) {
+        return msg.to_string();
+    }
+
+    match json_value.as_object() {
+        Some(obj) => obj
+            .iter()
+            .filter(|(k, _)| k.as_str() != "error")
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => json_value.to_string(),
+    }
+}
+
+fn check_server_error(json_value: &Value, status: StatusCode) -> Result<(), ClientError> {
+    if let Some(v) = json_value.get("error") {
+        if v.as_bool() == Some(true) {
+            return Err(ClientError::Server {
+                status,
+                message: server_error_message(json_value),
+            });
+        }
+    }
+
+    if !status.is_success() {
+        return Err(ClientError::HttpStatus(status));
+    }
+
+    Ok(())
+}
+
+fn field(mut json_value: Value, name: &str) -> Result<Value, ClientError> {
+    json_value
+        .get_mut(name)
+        .map(Value::take)
+        .ok_or_else(|| ClientError::MalformedResponse(format!("field {} not found", name)))
+}
+
+fn parse<T: DeserializeOwned>(mut json_value: Value) -> Result<T, ClientError> {
+    if let Some(obj) = json_value.as_object_mut() {
+        obj.remove("error");
+    }
+
+    serde_json::from_value(json_value).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// Failed to connect or send request
+    Transport(reqwest::Error),
+    /// Request timed out
+    Timeout(reqwest::Error),
+    /// Server answered with unsuccessful HTTP status without error message
+    HttpStatus(StatusCode),
+    /// Server reported an error in its response
+    Server { status: StatusCode, message: String },
+    /// Server response can not be understood
+    MalformedResponse(String),
+    /// Client can not be built with given settings
+    InvalidConfig(String),
+    /// Replayed cassette has no response to request, or recording failed
+    Cassette(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "Failed to send request: {}", e),
+            ClientError::Timeout(e) => write!(f, "Request timed out: {}", e),
+            ClientError::HttpStatus(status) => write!(f, "Server returned HTTP status {}", status),
+            ClientError::Server { status, message } => {
+                write!(f, "Server returned a error ({}): {}", status, message)
+            }
+            ClientError::MalformedResponse(msg) => write!(f, "Malformed server response: {}", msg),
+            ClientError::InvalidConfig(msg) => write!(f, "Invalid client settings: {}", msg),
+            ClientError::Cassette(msg) => write!(f, "Cassette error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(e) | ClientError::Timeout(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ClientError::Timeout(e)
+        } else {
+            ClientError::Transport(e)
+        }
+    }
+}
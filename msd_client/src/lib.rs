@@ -0,0 +1,33 @@
+//! Typed client of MSD server REST API
+//!
+//! ```no_run
+//! use msd_client::{CacheFilter, MsdClient};
+//!
+//! let client = MsdClient::builder("http://127.0.0.1:8000/api/v1")
+//!     .api_key("my-key")
+//!     .build()?;
+//!
+//! let filter = CacheFilter {
+//!     user_id: Some(3),
+//!     ..Default::default()
+//! };
+//! for cache in client.find_caches(&filter)? {
+//!     println!("{}: {}", cache.id, cache.descrip);
+//! }
+//! # Ok::<(), msd_client::ClientError>(())
+//! ```
+
+mod cassette;
+mod client;
+mod error;
+mod models;
+mod redact;
+mod retry;
+mod trace;
+
+pub use client::{CachePages, MsdClient, MsdClientBuilder, API_KEY_HEADER};
+pub use error::ClientError;
+pub use models::*;
+pub use redact::{redact, redact_header, redact_in_place, REDACTED, SECRET_FIELDS};
+pub use retry::RetryPolicy;
+pub use trace::{TraceEvent, TracedResponse};
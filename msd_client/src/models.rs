@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+/// User account
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct User {
+    pub id: i32,
+    pub login: String,
+    pub email: String,
+}
+
+/// Data of a new user
+#[derive(Serialize, Debug, Clone)]
+pub struct NewUser {
+    pub login: String,
+    pub email: String,
+    pub password: String,
+}
+
+/// Result of user creation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CreatedUser {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    /// Default API key of new user
+    pub api_key: String,
+}
+
+/// Changes of user. `None` fields are left as is.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct UserChanges {
+    pub email: Option<String>,
+    pub password: Option<String>,
+}
+
+/// API key of user
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiKey {
+    /// Number of key
+    pub nmb: usize,
+    pub api_key: String,
+}
+
+/// Geocache
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cache {
+    pub id: i32,
+    /// ID of user who owns cache
+    #[serde(default)]
+    pub owner: Option<i32>,
+    pub lat: f64,
+    pub long: f64,
+    pub descrip: String,
+    pub hint: String,
+}
+
+/// Result of cache creation. Only ID of the new cache is relied on, the
+/// cache itself is read by [`crate::MsdClient::get_cache`].
+#[derive(Deserialize, Debug)]
+pub(crate) struct CreatedCache {
+    pub id: i32,
+}
+
+/// Data of a new cache
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewCache {
+    pub lat: f64,
+    pub long: f64,
+    pub descrip: String,
+    pub hint: String,
+}
+
+/// Changes of cache. `None` fields are left as is.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CacheChanges {
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub descrip: Option<String>,
+    pub hint: Option<String>,
+}
+
+/// Conditions of cache search. `None` fields are not used.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CacheFilter {
+    /// Owner of caches
+    pub user_id: Option<i32>,
+    pub min_lat: Option<f64>,
+    pub max_lat: Option<f64>,
+    pub min_long: Option<f64>,
+    pub max_long: Option<f64>,
+    /// Maximum number of caches in response
+    pub limit: Option<usize>,
+    /// Number of matching caches to skip
+    pub offset: Option<usize>,
+}
+
+/// Request as it is sent, or would be sent in dry-run mode
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PreparedRequest {
+    pub method: String,
+    pub url: String,
+    /// Headers in order of sending, including api key
+    pub headers: Vec<(String, String)>,
+    /// JSON body
+    pub body: Option<serde_json::Value>,
+}
+
+/// Result of request changing data on server
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome<T> {
+    /// Request was sent, value is read from response
+    Sent(T),
+    /// Request was not sent because client is in dry-run mode
+    Prepared(PreparedRequest),
+}
+
+impl<T> Outcome<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Outcome<U> {
+        match self {
+            Outcome::Sent(value) => Outcome::Sent(f(value)),
+            Outcome::Prepared(request) => Outcome::Prepared(request),
+        }
+    }
+
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Outcome<U>, E> {
+        match self {
+            Outcome::Sent(value) => f(value).map(Outcome::Sent),
+            Outcome::Prepared(request) => Ok(Outcome::Prepared(request)),
+        }
+    }
+
+    /// Value of sent request, `None` in dry-run mode
+    pub fn sent(self) -> Option<T> {
+        match self {
+            Outcome::Sent(value) => Some(value),
+            Outcome::Prepared(_) => None,
+        }
+    }
+}
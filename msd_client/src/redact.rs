@@ -0,0 +1,44 @@
+//! Hiding secrets of requests and responses before they are saved or printed
+
+use serde_json::Value;
+
+use crate::client::API_KEY_HEADER;
+
+/// Fields of request and response bodies holding secrets
+pub static SECRET_FIELDS: &[&str] = &["password", "api_key", "key"];
+
+/// Text replacing secret values
+pub static REDACTED: &str = "<redacted>";
+
+/// Replaces values of secret fields at any depth of `json`
+pub fn redact_in_place(json: &mut Value) {
+    match json {
+        Value::Object(obj) => {
+            for (k, v) in obj.iter_mut() {
+                if SECRET_FIELDS.contains(&k.as_str()) && !v.is_null() {
+                    *v = Value::String(REDACTED.to_string());
+                } else {
+                    redact_in_place(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_in_place),
+        _ => {}
+    }
+}
+
+/// Copy of `json` with values of secret fields replaced
+pub fn redact(json: &Value) -> Value {
+    let mut json = json.clone();
+    redact_in_place(&mut json);
+    json
+}
+
+/// Value of header, replaced when the header carries api key
+pub fn redact_header(name: &str, value: &str) -> String {
+    if name.eq_ignore_ascii_case(API_KEY_HEADER) {
+        REDACTED.to_string()
+    } else {
+        value.to_string()
+    }
+}
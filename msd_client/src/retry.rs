@@ -0,0 +1,112 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use reqwest::{blocking::Response, header, Method, StatusCode};
+
+/// When and how often failed requests are repeated.
+///
+/// Requests are retried on connection errors, timeouts and HTTP statuses
+/// 502, 503 and 504. Delay grows exponentially from `base_delay` up to
+/// `max_delay`; `Retry-After` of server is used when it asks to wait longer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt. 0 disables retries.
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper limit of any delay, including one from `Retry-After`
+    pub max_delay: Duration,
+    /// Randomize delays in `[delay / 2, delay]` to spread retries of many clients
+    pub jitter: bool,
+    /// Retry POST requests too. They are not idempotent, so a retried
+    /// request may create a duplicate object.
+    pub retry_post: bool,
+}
+
+impl Default for RetryPolicy {
+    /// 3 retries with delays from 250 ms to 10 s, jitter, POST is not retried
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            retry_post: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy without retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn allows(&self, method: &Method) -> bool {
+        *method != Method::POST || self.retry_post
+    }
+
+    /// Delay before retry number `attempt` (starting from 0) after `result`.
+    /// `None` means result must not be retried.
+    pub(crate) fn delay(
+        &self,
+        attempt: u32,
+        result: &Result<Response, reqwest::Error>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let retry_after = match result {
+            Ok(response) if is_retryable_status(response.status()) => retry_after(response),
+            Ok(_) => return None,
+            Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => None,
+            Err(_) => return None,
+        };
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let backoff = if self.jitter {
+            jittered(backoff)
+        } else {
+            backoff
+        };
+
+        Some(match retry_after {
+            Some(d) => d.max(backoff).min(self.max_delay),
+            None => backoff,
+        })
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// `Retry-After` in seconds. HTTP dates are not supported and ignored.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Random delay in `[delay / 2, delay]`
+fn jittered(delay: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let half = delay / 2;
+    let extra = half.as_nanos() as u64;
+    if extra == 0 {
+        return delay;
+    }
+    half + Duration::from_nanos(random % (extra + 1))
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use crate::models::PreparedRequest;
+
+/// Step of request handling reported to [`MsdClientBuilder::trace`](crate::MsdClientBuilder::trace).
+/// Attempts are numbered from 0, every retry sends the request again.
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    /// Request is being sent
+    Request {
+        attempt: u32,
+        request: PreparedRequest,
+    },
+    /// Response was received and its body read
+    Response {
+        attempt: u32,
+        response: TracedResponse,
+    },
+    /// Request failed without response, or body could not be read
+    Failed {
+        attempt: u32,
+        error: String,
+        elapsed: Duration,
+    },
+    /// Request will be sent again after `delay`
+    Retry { attempt: u32, delay: Duration },
+}
+
+/// Response as received, body is not parsed
+#[derive(Debug, Clone)]
+pub struct TracedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Raw body, invalid UTF-8 is replaced
+    pub body: String,
+    /// Time from sending request to receiving response headers
+    pub headers_time: Duration,
+    /// Time from sending request to reading whole body
+    pub total_time: Duration,
+}
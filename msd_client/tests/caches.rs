@@ -0,0 +1,38 @@
+//! Cache requests against a local HTTP server answering with scripted responses
+
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use msd_client::{MsdClient, NewCache, RetryPolicy};
+
+use common::{ok, serve};
+
+#[test]
+fn created_cache_is_read_back() {
+    let (url, count) = serve(vec![
+        // Nothing but ID of the new cache is expected in response
+        ok(r#"{"error":false,"id":7}"#),
+        ok(
+            r#"{"error":false,"caches":{"id":7,"owner":2,"lat":1.5,"long":2.5,"descrip":"Oak","hint":"Roots"}}"#,
+        ),
+    ]);
+    let client = MsdClient::builder(url)
+        .retry(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let cache = client
+        .create_cache(&NewCache {
+            lat: 1.5,
+            long: 2.5,
+            descrip: "Oak".to_string(),
+            hint: "Roots".to_string(),
+        })
+        .unwrap()
+        .sent()
+        .unwrap();
+    assert_eq!(cache.id, 7);
+    assert_eq!(cache.owner, Some(2));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,74 @@
+//! Recording responses of a local HTTP server and replaying them without it
+
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use msd_client::{ClientError, MsdClient, RetryPolicy};
+
+use common::{ok, serve};
+
+static KEYS_JSON: &str =
+    r#"{"error":false,"keys":[{"nmb":1,"api_key":"k1"},{"nmb":2,"api_key":"k2"}]}"#;
+static CACHE_JSON: &str = r#"{"error":false,"caches":{"id":3,"owner":1,"lat":1.5,"long":2.5,"descrip":"Oak","hint":"Roots"}}"#;
+
+fn cassette_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("msd-cassette-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn builder(url: &str) -> msd_client::MsdClientBuilder {
+    MsdClient::builder(url)
+        .api_key("secret-key")
+        .retry(RetryPolicy::none())
+}
+
+#[test]
+fn replays_recorded_responses() {
+    let dir = cassette_dir("replay");
+    let (url, _) = serve(vec![ok(KEYS_JSON), ok(CACHE_JSON)]);
+
+    let client = builder(&url).record(&dir).build().unwrap();
+    assert_eq!(client.list_keys(1).unwrap().len(), 2);
+    assert_eq!(client.get_cache(3).unwrap().descrip, "Oak");
+
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        ["0001-get-user-1-keys.json", "0002-get-cache-3.json"]
+    );
+
+    let recorded = fs::read_to_string(dir.join(&files[0])).unwrap();
+    assert!(!recorded.contains("secret-key"));
+    assert!(!recorded.contains("k1"));
+
+    // Server is gone, responses come from files
+    let client = builder(&url).replay(&dir).build().unwrap();
+    let keys = client.list_keys(1).unwrap();
+    assert_eq!(keys[1].nmb, 2);
+    assert_eq!(client.get_cache(3).unwrap().hint, "Roots");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn fails_on_unmatched_request() {
+    let dir = cassette_dir("unmatched");
+    let (url, _) = serve(vec![ok(CACHE_JSON)]);
+
+    let client = builder(&url).record(&dir).build().unwrap();
+    client.get_cache(3).unwrap();
+
+    let client = builder(&url).replay(&dir).build().unwrap();
+    assert!(matches!(client.get_cache(4), Err(ClientError::Cassette(_))));
+    client.get_cache(3).unwrap();
+    // Every recorded response is served once
+    assert!(matches!(client.get_cache(3), Err(ClientError::Cassette(_))));
+
+    let _ = fs::remove_dir_all(&dir);
+}
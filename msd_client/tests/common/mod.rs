@@ -0,0 +1,72 @@
+//! Local servers answering requests of the client with scripted responses
+#![allow(dead_code)]
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+pub static USER_JSON: &str = r#"{"id":1,"login":"tester","email":"tester@example.com"}"#;
+
+/// Serves one HTTP connection per response. Returns base URL and request counter.
+pub fn serve(responses: Vec<String>) -> (String, Arc<AtomicUsize>) {
+    let (port, count) = serve_with(responses, Some);
+    (format!("http://127.0.0.1:{}/api/v1", port), count)
+}
+
+/// Serves one connection per response over the stream returned by `accept`,
+/// e.g. after TLS handshake. Connection is dropped when `accept` gives `None`.
+/// Returns port and request counter.
+pub fn serve_with<S, F>(responses: Vec<String>, accept: F) -> (u16, Arc<AtomicUsize>)
+where
+    S: Read + Write,
+    F: Fn(TcpStream) -> Option<S> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+
+    thread::spawn(move || {
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = match accept(stream) {
+                Some(s) => s,
+                None => continue,
+            };
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            // Small request body is sent together with headers and ignored
+            counter.fetch_add(1, Ordering::SeqCst);
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.flush();
+        }
+    });
+
+    (port, count)
+}
+
+pub fn response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status,
+        body.len(),
+        headers,
+        body
+    )
+}
+
+pub fn ok(body: &str) -> String {
+    response("200 OK", "", body)
+}
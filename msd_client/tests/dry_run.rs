@@ -0,0 +1,64 @@
+//! Dry-run mode returns prepared requests instead of sending them
+
+use msd_client::{ClientError, MsdClient, NewCache, Outcome, RetryPolicy};
+use serde_json::json;
+
+fn client() -> MsdClient {
+    // Nothing listens on discard port, so a sent request fails
+    MsdClient::builder("http://127.0.0.1:9/api/v1")
+        .api_key("secret")
+        .user_agent("tester")
+        .retry(RetryPolicy::none())
+        .dry_run(true)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn returns_prepared_request() {
+    let cache = NewCache {
+        lat: 1.5,
+        long: 2.5,
+        descrip: "Oak".to_string(),
+        hint: "Roots".to_string(),
+    };
+
+    let request = match client().create_cache(&cache) {
+        Ok(Outcome::Prepared(r)) => r,
+        other => panic!("Unexpected result {:?}", other),
+    };
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.url, "http://127.0.0.1:9/api/v1/cache/");
+    assert_eq!(request.body, Some(json!(cache)));
+
+    let header = |name: &str| {
+        request
+            .headers
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    };
+    assert_eq!(header("x-api-key"), Some("secret"));
+    assert_eq!(header("user-agent"), Some("tester"));
+    assert_eq!(header("content-type"), Some("application/json"));
+}
+
+#[test]
+fn request_without_body() {
+    match client().delete_cache(5) {
+        Ok(Outcome::Prepared(r)) => {
+            assert_eq!(r.method, "DELETE");
+            assert_eq!(r.url, "http://127.0.0.1:9/api/v1/cache/5");
+            assert_eq!(r.body, None);
+        }
+        other => panic!("Unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn sends_get_requests() {
+    assert!(matches!(
+        client().get_cache(5),
+        Err(ClientError::Transport(_))
+    ));
+}
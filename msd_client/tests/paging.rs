@@ -0,0 +1,74 @@
+//! Walking caches page by page against a local HTTP server answering with scripted pages
+
+mod common;
+
+use std::sync::atomic::Ordering;
+
+use msd_client::{Cache, CacheFilter, MsdClient, RetryPolicy};
+
+use common::{ok, serve};
+
+/// Page of caches with given IDs
+fn page(ids: &[i32]) -> String {
+    let caches: Vec<_> = ids
+        .iter()
+        .map(|id| {
+            format!(
+                r#"{{"id":{0},"owner":1,"lat":{0}.0,"long":{0}.0,"descrip":"Cache {0}","hint":"h"}}"#,
+                id
+            )
+        })
+        .collect();
+    ok(&format!(r#"{{"caches":[{}]}}"#, caches.join(",")))
+}
+
+fn ids(pages: &[Vec<Cache>]) -> Vec<Vec<i32>> {
+    pages
+        .iter()
+        .map(|p| p.iter().map(|c| c.id).collect())
+        .collect()
+}
+
+fn client(url: String) -> MsdClient {
+    MsdClient::builder(url)
+        .retry(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn stops_on_empty_page() {
+    let (url, count) = serve(vec![page(&[1, 2]), page(&[3, 4]), page(&[])]);
+
+    let pages: Vec<_> = client(url)
+        .find_caches_paged(&CacheFilter::default(), 2)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(ids(&pages), vec![vec![1, 2], vec![3, 4]]);
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn continues_after_short_page() {
+    // Server caps page size at 2 while 3 caches per page are requested
+    let (url, count) = serve(vec![page(&[1, 2]), page(&[3, 4]), page(&[5]), page(&[])]);
+
+    let pages: Vec<_> = client(url)
+        .find_caches_paged(&CacheFilter::default(), 3)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(ids(&pages), vec![vec![1, 2], vec![3, 4], vec![5]]);
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn stops_when_server_ignores_offset() {
+    let (url, count) = serve(vec![page(&[1, 2]); 5]);
+
+    let pages: Vec<_> = client(url)
+        .find_caches_paged(&CacheFilter::default(), 2)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(ids(&pages), vec![vec![1, 2]]);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
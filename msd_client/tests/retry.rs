@@ -0,0 +1,190 @@
+//! Retries and tracing against a local HTTP server answering with scripted responses
+
+mod common;
+
+use std::{
+    net::TcpListener,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use msd_client::{ClientError, MsdClient, NewUser, RetryPolicy, TraceEvent};
+use reqwest::StatusCode;
+
+use common::{ok, response, serve, USER_JSON};
+
+fn unavailable() -> String {
+    response("503 Service Unavailable", "", "")
+}
+
+fn ok_user() -> String {
+    ok(USER_JSON)
+}
+
+fn policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(100),
+        jitter: false,
+        retry_post: false,
+    }
+}
+
+#[test]
+fn retries_unavailable_server() {
+    let (url, count) = serve(vec![unavailable(), unavailable(), ok_user()]);
+    let client = MsdClient::builder(url).retry(policy(3)).build().unwrap();
+
+    assert_eq!(client.get_user(1).unwrap().id, 1);
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn gives_up_after_max_retries() {
+    let (url, count) = serve(vec![unavailable(), unavailable(), ok_user()]);
+    let client = MsdClient::builder(url).retry(policy(1)).build().unwrap();
+
+    let err = client.get_user(1).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ClientError::HttpStatus(StatusCode::SERVICE_UNAVAILABLE)
+        ),
+        "{:?}",
+        err
+    );
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn does_not_retry_other_statuses() {
+    let (url, count) = serve(vec![
+        response("500 Internal Server Error", "", ""),
+        ok_user(),
+    ]);
+    let client = MsdClient::builder(url).retry(policy(3)).build().unwrap();
+
+    assert!(client.get_user(1).is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn post_is_retried_only_when_allowed() {
+    let user = NewUser {
+        login: "tester".to_string(),
+        email: "tester@example.com".to_string(),
+        password: "secret".to_string(),
+    };
+    let created = response("200 OK", "", r#"{"id":1,"api_key":"key"}"#);
+
+    let (url, count) = serve(vec![unavailable(), created.clone()]);
+    let client = MsdClient::builder(url).retry(policy(3)).build().unwrap();
+    assert!(client.create_user(&user).is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    let (url, count) = serve(vec![unavailable(), created]);
+    let client = MsdClient::builder(url)
+        .retry(RetryPolicy {
+            retry_post: true,
+            ..policy(3)
+        })
+        .build()
+        .unwrap();
+    assert_eq!(
+        client.create_user(&user).unwrap().sent().unwrap().api_key,
+        "key"
+    );
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn honors_retry_after() {
+    let (url, _) = serve(vec![
+        response("503 Service Unavailable", "Retry-After: 1\r\n", ""),
+        ok_user(),
+    ]);
+    let client = MsdClient::builder(url)
+        .retry(RetryPolicy {
+            max_delay: Duration::from_secs(5),
+            ..policy(1)
+        })
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+    assert!(client.get_user(1).is_ok());
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+#[test]
+fn retries_connection_errors() {
+    // Nothing listens on the port after listener is dropped
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let client = MsdClient::builder(format!("http://127.0.0.1:{}/api/v1", port))
+        .retry(policy(2))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+    let err = client.get_user(1).unwrap_err();
+    assert!(matches!(err, ClientError::Transport(_)), "{:?}", err);
+    // Two delays of 10 and 20 ms
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
+
+#[test]
+fn traces_attempts_with_raw_bodies() {
+    let (url, _) = serve(vec![
+        unavailable(),
+        response("200 OK", "X-Test: yes\r\n", "not json"),
+    ]);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let traced = events.clone();
+    let client = MsdClient::builder(url)
+        .api_key("secret")
+        .retry(policy(1))
+        .trace(move |e| traced.lock().unwrap().push(e.clone()))
+        .build()
+        .unwrap();
+
+    let err = client.get_user(1).unwrap_err();
+    assert!(
+        matches!(err, ClientError::MalformedResponse(_)),
+        "{:?}",
+        err
+    );
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 5, "{:?}", events);
+    match &events[0] {
+        TraceEvent::Request { attempt, request } => {
+            assert_eq!(*attempt, 0);
+            assert_eq!(request.method, "GET");
+            assert!(request.url.ends_with("/user/1"));
+        }
+        e => panic!("Unexpected event {:?}", e),
+    }
+    match &events[1] {
+        TraceEvent::Response { response, .. } => assert_eq!(response.status, 503),
+        e => panic!("Unexpected event {:?}", e),
+    }
+    assert!(matches!(events[2], TraceEvent::Retry { attempt: 0, .. }));
+    assert!(matches!(events[3], TraceEvent::Request { attempt: 1, .. }));
+    match &events[4] {
+        TraceEvent::Response { attempt, response } => {
+            assert_eq!(*attempt, 1);
+            assert_eq!(response.status, 200);
+            assert_eq!(response.body, "not json");
+            assert!(response
+                .headers
+                .contains(&("x-test".to_string(), "yes".to_string())));
+            assert!(response.headers_time <= response.total_time);
+        }
+        e => panic!("Unexpected event {:?}", e),
+    }
+}
@@ -0,0 +1,139 @@
+//! HTTPS requests against a local server with a certificate of a test CA.
+//!
+//! Fixtures in `tests/fixtures` were generated with openssl: `ca.pem` is a
+//! self-signed CA, `server.pem`/`server.key` is signed by it for `localhost`
+//! and `127.0.0.1`, `client.pem`/`client.key` is signed by it for client
+//! authentication. `untrusted.pem`/`untrusted.key` is a self-signed client
+//! certificate. Key of the CA is not kept.
+
+mod common;
+
+use msd_client::{ClientError, MsdClient};
+use native_tls::{Identity, TlsAcceptor};
+use openssl::{
+    nid::Nid,
+    pkey::PKey,
+    ssl::{SslAcceptor, SslMethod, SslVerifyMode},
+    x509::{X509VerifyResult, X509},
+};
+
+use common::{ok, serve_with, USER_JSON};
+
+static CA_PEM: &[u8] = include_bytes!("fixtures/ca.pem");
+static SERVER_PEM: &[u8] = include_bytes!("fixtures/server.pem");
+static SERVER_KEY: &[u8] = include_bytes!("fixtures/server.key");
+static CLIENT_PEM: &[u8] = include_bytes!("fixtures/client.pem");
+static CLIENT_KEY: &[u8] = include_bytes!("fixtures/client.key");
+static UNTRUSTED_PEM: &[u8] = include_bytes!("fixtures/untrusted.pem");
+static UNTRUSTED_KEY: &[u8] = include_bytes!("fixtures/untrusted.key");
+
+/// Serves one HTTPS request with a user object. Returns base URL of API.
+fn serve_once(host: &str) -> String {
+    let acceptor = TlsAcceptor::new(Identity::from_pkcs8(SERVER_PEM, SERVER_KEY).unwrap()).unwrap();
+    // Handshake fails when client does not trust the certificate
+    let (port, _) = serve_with(vec![ok(USER_JSON)], move |s| acceptor.accept(s).ok());
+    format!("https://{}:{}/api/v1", host, port)
+}
+
+/// Serves one HTTPS request with a user object to client presenting
+/// certificate `MSD Test Client` of the test CA. Returns base URL of API.
+fn serve_mutual() -> String {
+    let ca = X509::from_pem(CA_PEM).unwrap();
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor
+        .set_certificate(&X509::from_pem(SERVER_PEM).unwrap())
+        .unwrap();
+    acceptor
+        .set_private_key(&PKey::private_key_from_pem(SERVER_KEY).unwrap())
+        .unwrap();
+    acceptor.add_client_ca(&ca).unwrap();
+    acceptor.cert_store_mut().add_cert(ca).unwrap();
+    acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    let acceptor = acceptor.build();
+
+    // Handshake fails without a client certificate chaining to the CA
+    let (port, _) = serve_with(vec![ok(USER_JSON)], move |s| {
+        let stream = acceptor.accept(s).ok()?;
+        let ssl = stream.ssl();
+        let cert = ssl.peer_certificate()?;
+        let name = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next()?;
+        let trusted = ssl.verify_result() == X509VerifyResult::OK;
+        (trusted && name.data().as_slice() == b"MSD Test Client").then_some(stream)
+    });
+    format!("https://localhost:{}/api/v1", port)
+}
+
+#[test]
+fn trusts_custom_ca() {
+    let client = MsdClient::builder(serve_once("localhost"))
+        .ca_certs_pem(CA_PEM)
+        .build()
+        .unwrap();
+
+    let user = client.get_user(1).unwrap();
+    assert_eq!(user.login, "tester");
+}
+
+#[test]
+fn rejects_unknown_ca() {
+    let client = MsdClient::new(serve_once("localhost")).unwrap();
+
+    let err = client.get_user(1).unwrap_err();
+    assert!(matches!(err, ClientError::Transport(_)), "{:?}", err);
+}
+
+#[test]
+fn insecure_accepts_unknown_ca() {
+    let client = MsdClient::builder(serve_once("127.0.0.1"))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    assert_eq!(client.get_user(1).unwrap().id, 1);
+}
+
+#[test]
+fn presents_client_identity() {
+    let client = MsdClient::builder(serve_mutual())
+        .ca_certs_pem(CA_PEM)
+        .client_identity_pem(CLIENT_PEM, CLIENT_KEY)
+        .build()
+        .unwrap();
+
+    assert_eq!(client.get_user(1).unwrap().id, 1);
+}
+
+#[test]
+fn rejected_without_trusted_identity() {
+    let anonymous = MsdClient::builder(serve_mutual())
+        .ca_certs_pem(CA_PEM)
+        .build()
+        .unwrap();
+    let err = anonymous.get_user(1).unwrap_err();
+    assert!(matches!(err, ClientError::Transport(_)), "{:?}", err);
+
+    let untrusted = MsdClient::builder(serve_mutual())
+        .ca_certs_pem(CA_PEM)
+        .client_identity_pem(UNTRUSTED_PEM, UNTRUSTED_KEY)
+        .build()
+        .unwrap();
+    let err = untrusted.get_user(1).unwrap_err();
+    assert!(matches!(err, ClientError::Transport(_)), "{:?}", err);
+}
+
+#[test]
+fn invalid_pem_is_config_error() {
+    let err = MsdClient::builder("https://localhost/api/v1")
+        .ca_certs_pem(&b"not a certificate"[..])
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, ClientError::InvalidConfig(_)), "{:?}", err);
+
+    let err = MsdClient::builder("https://localhost/api/v1")
+        .client_identity_pem(CLIENT_PEM, &b"not a key"[..])
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, ClientError::InvalidConfig(_)), "{:?}", err);
+}
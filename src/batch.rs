@@ -0,0 +1,221 @@
+//! Running commands from a script file with one client
+//!
+//! Lines are parsed like lines of shell, so `set` works too. Empty lines and
+//! lines starting with `#` are skipped. Before parsing, `$NAME` and `${NAME}`
+//! are replaced by variables:
+//! * `$LAST_ID` - ID returned by the last `user create` or `cache create`
+//! * `$LAST_KEY` - api key returned by the last `user create` or `user keys generate`
+//! * other names - environment variables
+//!
+//! With `--dry-run` nothing is created, so `$LAST_ID` and `$LAST_KEY` are not set.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+};
+
+use clap::StructOpt;
+use serde_json::Value;
+
+use crate::{
+    cli::{BatchArgs, CacheCommand, Command, GlobalArgs, ShellLine, UserCommand, UserKeysCommand},
+    error::CliError,
+    output::print_document,
+    shell::Session,
+};
+
+/// Runs script. Without `--continue-on-error` the first failed command stops it.
+pub fn run(args: &BatchArgs, global: GlobalArgs) -> Result<(), CliError> {
+    let script = read_script(args)?;
+    let mut session = Session::new(global)?;
+    let mut vars = BTreeMap::new();
+
+    let mut reports = Vec::new();
+    let mut failed = 0;
+    let mut stopped = None;
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if session.global().output.is_text() {
+            println!("> {}", line);
+        }
+
+        let captured = RefCell::new(None);
+        let res = execute(&mut session, line, &mut vars, &captured);
+        reports.push(json!({
+            "line": i + 1,
+            "command": line,
+            "result": captured.take(),
+            "error": res.as_ref().err().map(|e| e.to_string()),
+        }));
+
+        match res {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) if args.continue_on_error => {
+                eprintln!("Error: line {}: {}", i + 1, e);
+                failed += 1;
+            }
+            Err(e) => {
+                failed += 1;
+                stopped = Some((i + 1, e));
+                break;
+            }
+        }
+    }
+
+    let succeeded = reports.len() - failed;
+    if session.global().output.is_text() {
+        println!(
+            "Batch finished: {} commands succeeded, {} failed",
+            succeeded, failed
+        );
+    } else {
+        let doc = json!({ "commands": reports, "succeeded": succeeded, "failed": failed });
+        print_document(session.global().output, &doc);
+    }
+
+    match stopped {
+        Some((line, e)) => Err(CliError::Partial(format!("Line {}: {}", line, e))),
+        None if failed > 0 => Err(CliError::Partial(format!(
+            "{} of {} commands failed",
+            failed,
+            reports.len()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn read_script(args: &BatchArgs) -> Result<String, CliError> {
+    let mut script = String::new();
+    let res = if args.file.as_os_str() == "-" {
+        io::stdin().read_to_string(&mut script).map(|_| ())
+    } else {
+        fs::read_to_string(&args.file).map(|s| script = s)
+    };
+
+    res.map_err(|e| CliError::Local(format!("Failed to read {}: {}", args.file.display(), e)))?;
+    Ok(script)
+}
+
+/// Executes line and updates variables. Returns `false` when batch must stop.
+fn execute(
+    session: &mut Session,
+    line: &str,
+    vars: &mut BTreeMap<&'static str, String>,
+    captured: &RefCell<Option<Value>>,
+) -> Result<bool, CliError> {
+    let line = substitute(line, vars)?;
+    let words = shell_words::split(&line).map_err(|e| CliError::Usage(e.to_string()))?;
+    if let Some(res) = session.builtin(&words) {
+        return res;
+    }
+
+    let line = ShellLine::try_parse_from(&words).map_err(|e| {
+        // Usage and help hints of clap do not fit into report of line
+        let msg = e.to_string();
+        let first = msg.lines().next().unwrap_or_default();
+        CliError::Usage(first.trim_start_matches("error: ").to_string())
+    })?;
+    session.run(&line.command, Some(captured))?;
+
+    let result = captured.borrow();
+    let field = |name| {
+        result
+            .as_ref()
+            .and_then(|r| r.get(name))
+            .filter(|v| !v.is_null())
+            .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+    };
+    // Values of earlier commands are dropped, so they are not used by mistake
+    let (id, key) = match &line.command {
+        Command::User(u) => match &u.command {
+            UserCommand::Create(_) => (Some(field("id")), Some(field("api_key"))),
+            UserCommand::Keys(k) => match &k.command {
+                UserKeysCommand::Generate(_) => (None, Some(field("key"))),
+                _ => (None, None),
+            },
+            _ => (None, None),
+        },
+        Command::Cache(c) => match &c.command {
+            CacheCommand::Create(_) => (Some(field("id")), None),
+            _ => (None, None),
+        },
+        _ => (None, None),
+    };
+    for (name, value) in [("LAST_ID", id), ("LAST_KEY", key)] {
+        match value {
+            Some(Some(v)) => {
+                vars.insert(name, v);
+            }
+            Some(None) => {
+                vars.remove(name);
+            }
+            None => {}
+        }
+    }
+
+    Ok(true)
+}
+
+/// Replaces `$NAME` and `${NAME}` by variables or environment variables.
+/// `$` not followed by a name is kept.
+fn substitute(line: &str, vars: &BTreeMap<&'static str, String>) -> Result<String, CliError> {
+    let mut result = String::new();
+    let mut rest = line;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => return Err(CliError::Usage(format!("Unclosed ${{ in: {}", line))),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+
+        if name.is_empty() {
+            result.push('$');
+        } else {
+            let value = vars
+                .get(name)
+                .cloned()
+                .or_else(|| std::env::var(name).ok())
+                .ok_or_else(|| CliError::Usage(format!("Variable ${} is not set", name)))?;
+            result.push_str(&value);
+        }
+        rest = &after[len..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_variables() {
+        let vars = BTreeMap::from([("LAST_ID", "7".to_string())]);
+
+        assert_eq!(
+            substitute("cache view --id $LAST_ID", &vars).unwrap(),
+            "cache view --id 7"
+        );
+        assert_eq!(substitute("x${LAST_ID}y $ 5$", &vars).unwrap(), "x7y $ 5$");
+        assert!(substitute("user view --id $LAST_KEY", &vars).is_err());
+        assert!(substitute("${LAST_ID", &vars).is_err());
+    }
+}
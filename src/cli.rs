@@ -1,32 +1,228 @@
-use clap::{Args, Parser, Subcommand};
-use serde::Serialize;
+use std::{env, path::PathBuf, time::Duration};
 
+use clap::{ArgAction, ArgEnum, Args, Parser, Subcommand};
+use clap_complete::Shell;
+use msd_client::{CacheFilter, RetryPolicy};
+
+use crate::{
+    config::Profile,
+    error::{CliError, EXIT_CODES_HELP},
+    export::ExportFormat,
+    geo::{parse_distance, parse_point, Point},
+    import::{parse_mapping, ColumnMapping, ImportFormat},
+    manifest::State,
+    output::OutputFormat,
+    secrets::{self, API_KEY_ENV},
+    table::Layout,
+};
+
+static DEFAULT_IP: &str = "127.0.0.1";
+static DEFAULT_PORT: u16 = 8000;
+
+/// Command line client of MSD server REST API
 #[derive(Parser, Debug)]
+#[clap(name = "msd-cli", version, after_help = EXIT_CODES_HELP)]
 pub struct MainCliArgs {
     /// Command to execute
     #[clap(subcommand)]
     pub command: Command,
 
-    /// Api key to access server
+    #[clap(flatten)]
+    pub global: GlobalArgs,
+}
+
+/// Options shared by all commands
+#[derive(Args, Debug, Clone)]
+pub struct GlobalArgs {
+    /// Api key to access server [env: MSD_API_KEY]
     #[clap(long, global = true)]
     pub api: Option<String>,
 
-    /// Api server IP address
-    #[clap(long, global = true, default_value = "127.0.0.1")]
-    pub ip: String,
+    /// Read api key from first line of file
+    #[clap(long, global = true, conflicts_with = "api")]
+    pub api_file: Option<PathBuf>,
 
-    /// Api server's port
-    #[clap(long, global = true, default_value = "8000")]
-    pub port: u16,
+    /// Full base URL of API, e.g. https://msd.example.com/api/v1
+    #[clap(long, global = true, conflicts_with_all = &["ip", "port", "tls"])]
+    pub base_url: Option<String>,
+
+    /// Api server IP address [default: 127.0.0.1]
+    #[clap(long, global = true)]
+    pub ip: Option<String>,
+
+    /// Api server's port [default: 8000]
+    #[clap(long, global = true)]
+    pub port: Option<u16>,
+
+    /// Use https scheme with --ip and --port
+    #[clap(long, global = true)]
+    pub tls: bool,
+
+    /// PEM bundle of CA certificates to trust, e.g. of internal CA
+    #[clap(long, global = true)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate for mutual TLS
+    #[clap(long, global = true, requires = "client-key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// PEM (PKCS#8) private key of client certificate
+    #[clap(long, global = true, requires = "client-cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// Do not verify server certificate. Insecure, use for testing only
+    #[clap(long, global = true)]
+    pub insecure: bool,
+
+    /// Connect timeout in seconds [default: 5]
+    #[clap(long, global = true)]
+    pub connect_timeout: Option<u64>,
+
+    /// Total timeout of one request attempt in seconds [default: none]
+    #[clap(long, global = true)]
+    pub timeout: Option<u64>,
+
+    /// Retries of requests failed with connection errors, timeouts or 502/503/504
+    #[clap(long, global = true, default_value = "3")]
+    pub retries: u32,
+
+    /// Delay before first retry in milliseconds, doubled for every next one
+    #[clap(long, global = true, default_value = "250")]
+    pub retry_delay: u64,
+
+    /// Maximum delay between retries in milliseconds, also limits Retry-After
+    #[clap(long, global = true, default_value = "10000")]
+    pub retry_max_delay: u64,
+
+    /// Do not randomize delays between retries
+    #[clap(long, global = true)]
+    pub no_jitter: bool,
+
+    /// Retry POST requests too. A retried request may create a duplicate
+    #[clap(long, global = true)]
+    pub retry_post: bool,
+
+    /// Print requests of create, change and delete commands instead of sending them.
+    /// Read-only requests are sent
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+
+    /// Print every request as curl command to stderr. With --dry-run requests
+    /// changing data are printed instead of being sent
+    #[clap(long, global = true)]
+    pub print_curl: bool,
 
-    /// Verbose mode. Print raw server responses
+    /// Print api key and passwords in curl commands. By default the key is
+    /// read from $MSD_API_KEY and passwords are redacted
     #[clap(long, global = true)]
-    pub verbose: bool,
+    pub curl_secrets: bool,
+
+    /// Trace requests to stderr: -v prints requests, statuses and responses,
+    /// -vv also headers, request bodies and timings
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Append every request, response and retry as JSON line to file. Secrets are redacted
+    #[clap(long, global = true)]
+    pub trace_file: Option<PathBuf>,
+
+    /// Save every request and its response to directory, one JSON file per request.
+    /// Api key and secret fields are not saved
+    #[clap(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Answer requests with responses saved by --record in directory, nothing is sent.
+    /// Request without saved response fails
+    #[clap(long, global = true, value_name = "DIR")]
+    pub replay: Option<PathBuf>,
+
+    /// Output format. Structured formats print one document on stdout
+    #[clap(long, global = true, arg_enum, default_value = "text")]
+    pub output: OutputFormat,
+
+    /// Path to config file [default: $XDG_CONFIG_HOME/msd-cli/config.toml]
+    #[clap(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Profile from config file to use
+    #[clap(long, global = true)]
+    pub profile: Option<String>,
+
+    /// File of api keys stored by login, used instead of system keyring
+    /// [default: keyring, or $XDG_DATA_HOME/msd-cli/credentials.json without it]
+    #[clap(long, global = true)]
+    pub credentials: Option<PathBuf>,
+
+    /// Journal of changes made on servers [default: $XDG_DATA_HOME/msd-cli/journal.jsonl]
+    #[clap(long, global = true)]
+    pub journal: Option<PathBuf>,
 }
 
-impl MainCliArgs {
+impl GlobalArgs {
     pub fn get_api_base(&self) -> String {
-        format!("http://{}:{}/api/v1", self.ip, self.port)
+        if let Some(url) = &self.base_url {
+            return url.trim_end_matches('/').to_string();
+        }
+
+        format!(
+            "{}://{}:{}/api/v1",
+            if self.tls { "https" } else { "http" },
+            self.ip.as_deref().unwrap_or(DEFAULT_IP),
+            self.port.unwrap_or(DEFAULT_PORT)
+        )
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.retries,
+            base_delay: Duration::from_millis(self.retry_delay),
+            max_delay: Duration::from_millis(self.retry_max_delay),
+            jitter: !self.no_jitter,
+            retry_post: self.retry_post,
+        }
+    }
+
+    /// Fills values not given on command line from profile.
+    /// Api key is taken from --api, --api-file, environment or profile.
+    pub fn apply_profile(&mut self, profile: &Profile) -> Result<(), CliError> {
+        if self.api.is_none() {
+            self.api = match &self.api_file {
+                Some(path) => Some(secrets::read_file(path)?),
+                None => env::var(API_KEY_ENV)
+                    .ok()
+                    .filter(|k| !k.is_empty())
+                    .or_else(|| profile.api_key.clone()),
+            };
+        }
+
+        // --ip, --port and --tls override base URL of profile
+        if self.base_url.is_none() && self.ip.is_none() && self.port.is_none() && !self.tls {
+            self.base_url = profile.base_url.clone();
+        }
+        if let Some(url) = &self.base_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(CliError::Usage(format!(
+                    "Base URL must start with http:// or https://: {}",
+                    url
+                )));
+            }
+        }
+
+        if self.connect_timeout.is_none() {
+            self.connect_timeout = profile.connect_timeout;
+        }
+        if self.timeout.is_none() {
+            self.timeout = profile.timeout;
+        }
+
+        if self.ca_cert.is_none() {
+            self.ca_cert = profile.ca_cert.clone();
+        }
+        if self.client_cert.is_none() {
+            self.client_cert = profile.client_cert.clone();
+            self.client_key = profile.client_key.clone();
+        }
+        Ok(())
     }
 }
 
@@ -37,6 +233,169 @@ pub enum Command {
 
     /// Manipulate caches
     Cache(CacheArgs),
+
+    /// Manage server profiles in config file
+    Config(ConfigArgs),
+
+    /// List changes recorded in journal
+    History(HistoryArgs),
+
+    /// Revert change recorded in journal
+    Undo(UndoArgs),
+
+    /// Store api key of user, so it is used when no other key is given
+    ///
+    /// Server has no login by email and password and does not tell the user
+    /// of a key, so the key is asked together with ID of its user. The key is
+    /// accepted when server lists keys of the user to it.
+    Login(LoginArgs),
+
+    /// Remove api key stored by login
+    Logout,
+
+    /// Show user of api key stored by login
+    Whoami,
+
+    /// Start interactive shell to run commands without restarting
+    Shell(ShellArgs),
+
+    /// Run commands from file, one per line, with one client
+    Batch(BatchArgs),
+
+    /// Print completion script for shell
+    Completions(CompletionsArgs),
+
+    /// Write man pages of all commands to directory
+    Man(ManArgs),
+
+    /// Run mock server with in-memory data for development and tests
+    MockServer(MockServerArgs),
+
+    /// Print IDs known to server, used by completion scripts
+    #[clap(hide = true)]
+    CompleteIds(CompleteIdsArgs),
+}
+
+impl Command {
+    /// Commands not talking to server. They work with a broken profile, so
+    /// config can be fixed by them.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Command::Config(_) | Command::Completions(_) | Command::Man(_)
+        )
+    }
+
+    /// Commands sending requests with api key stored by login, when no other
+    /// key is given. Login brings its own key, logout sends nothing and user
+    /// is created without a key, so the store is not read for them.
+    pub fn uses_stored_key(&self) -> bool {
+        !self.is_local()
+            && !matches!(
+                self,
+                Command::Login(_)
+                    | Command::Logout
+                    | Command::History(_)
+                    | Command::MockServer(_)
+                    | Command::User(UserArgs {
+                        command: UserCommand::Create(_)
+                    })
+            )
+    }
+}
+
+/// Line typed in shell. Global options are the ones of shell session.
+#[derive(Parser, Debug)]
+#[clap(no_binary_name = true, name = "msd")]
+pub struct ShellLine {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to generate script for
+    #[clap(arg_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Args, Debug)]
+pub struct ManArgs {
+    /// Directory to write pages to, e.g. /usr/local/share/man/man1
+    pub dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct CompleteIdsArgs {
+    /// Kind of IDs to print
+    #[clap(arg_enum)]
+    pub kind: IdKind,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdKind {
+    /// IDs of all caches
+    Caches,
+    /// IDs of users owning caches
+    Users,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// Show only last N entries
+    #[clap(short, long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct UndoArgs {
+    /// Number of journal entry to revert, see `history`
+    pub entry: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct MockServerArgs {
+    /// Address to listen on
+    #[clap(long, default_value = "127.0.0.1")]
+    pub bind: String,
+
+    /// Port to listen on, 0 picks a free one
+    #[clap(long, default_value = "8000")]
+    pub port: u16,
+
+    /// JSON file with users and caches, loaded on start and saved on every change
+    #[clap(long)]
+    pub state: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ShellArgs {
+    /// File with history of commands [default: $XDG_DATA_HOME/msd-cli/history]
+    #[clap(long)]
+    pub history_file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct LoginArgs {
+    /// ID of user owning the key
+    #[clap(long)]
+    pub id: i32,
+
+    /// Read api key from first line of stdin instead of asking it
+    #[clap(long)]
+    pub key_stdin: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// Script with a command per line, `-` reads stdin. Lines starting with `#`
+    /// are comments. $LAST_ID and $LAST_KEY are the ID and api key returned by
+    /// the last create command, other $NAME are environment variables
+    pub file: PathBuf,
+
+    /// Run remaining commands after a failed one
+    #[clap(long)]
+    pub continue_on_error: bool,
 }
 
 #[derive(Args, Debug)]
@@ -48,7 +407,7 @@ pub struct UserArgs {
 
 #[derive(Subcommand, Debug)]
 pub enum UserCommand {
-    /// Create a new user
+    /// Create a new user. Password is asked interactively if not given
     Create(UserCreateArgs),
     /// View a user
     View(UserViewArgs),
@@ -58,7 +417,7 @@ pub enum UserCommand {
     Keys(UserKeysArgs),
 }
 
-#[derive(Args, Debug, Serialize)]
+#[derive(Args, Debug)]
 pub struct UserCreateArgs {
     /// Displayed name of account
     #[clap(short, long)]
@@ -68,9 +427,8 @@ pub struct UserCreateArgs {
     #[clap(short, long)]
     pub email: String,
 
-    /// Password
-    #[clap(short, long)]
-    pub password: String,
+    #[clap(flatten)]
+    pub password: PasswordArgs,
 }
 
 #[derive(Args, Debug)]
@@ -80,20 +438,53 @@ pub struct UserViewArgs {
     pub id: i32,
 }
 
-#[derive(Args, Debug, Serialize)]
+#[derive(Args, Debug)]
 pub struct UserChangeArgs {
     /// ID of requested user to change
     #[clap(short, long)]
-    #[serde(skip_serializing)]
     pub id: i32,
 
     /// New email for user. Can be skipped
     #[clap(short, long)]
     pub email: Option<String>,
 
-    /// New password for user
-    #[clap(short, long)]
-    pub password: Option<String>,
+    #[clap(flatten)]
+    pub password: PasswordArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct PasswordArgs {
+    /// Password. Asked without echo if value is omitted. A value given here is visible in shell history
+    #[clap(short, long, min_values = 0, max_values = 1, value_name = "PASSWORD")]
+    pub password: Option<Option<String>>,
+
+    /// Read password from first line of stdin
+    #[clap(long, conflicts_with_all = &["password", "password-file"])]
+    pub password_stdin: bool,
+
+    /// Read password from first line of file
+    #[clap(long, conflicts_with = "password")]
+    pub password_file: Option<PathBuf>,
+}
+
+impl PasswordArgs {
+    /// Reads password from given source. If no source is given, password is
+    /// asked interactively when `required` and `None` is returned otherwise.
+    pub fn read(&self, required: bool) -> Result<Option<String>, CliError> {
+        if let Some(path) = &self.password_file {
+            return secrets::read_file(path).map(Some);
+        }
+        if self.password_stdin {
+            return secrets::read_stdin().map(Some);
+        }
+
+        match &self.password {
+            Some(Some(p)) => Ok(Some(p.clone())),
+            Some(None) => secrets::prompt_password(true).map(Some),
+            None if required => secrets::prompt_password(true).map(Some),
+            None => Ok(None),
+        }
+    }
 }
 
 #[derive(Args, Debug)]
@@ -129,6 +520,9 @@ pub struct UserKeysViewArgs {
     /// Number of key to view. If not present, a program displays all.
     #[clap(short, long)]
     pub nmb: Option<usize>,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
 }
 
 #[derive(Args, Debug)]
@@ -165,6 +559,15 @@ pub enum CacheCommand {
 
     /// Delete specified cache
     Delete(CacheDeleteArgs),
+
+    /// Create caches from GPX or CSV file
+    Import(CacheImportArgs),
+
+    /// Show changes making caches match manifest
+    Plan(CachePlanArgs),
+
+    /// Make caches match manifest
+    Apply(CacheApplyArgs),
 }
 
 #[derive(Args, Debug)]
@@ -182,7 +585,7 @@ pub struct CacheCreateArgs {
     pub hint: String,
 }
 
-#[derive(Args, Debug, Serialize)]
+#[derive(Args, Debug)]
 pub struct CacheFindArgs {
     /// Filter user id
     #[clap(long)]
@@ -201,25 +604,54 @@ pub struct CacheFindArgs {
     /// Part of bound condition
     #[clap(long)]
     pub max_long: Option<f64>,
-}
 
-#[derive(Serialize)]
-pub struct CacheFindArgsServer {
-    pub user_id: Option<i32>,
-    pub min_lat: Option<f64>,
-    pub max_lat: Option<f64>,
-    pub min_long: Option<f64>,
-    pub max_long: Option<f64>,
+    /// Search around point given as LAT,LONG. Requires --radius
+    #[clap(
+        long,
+        parse(try_from_str = parse_point),
+        requires = "radius",
+        conflicts_with_all = &["min-lat", "max-lat", "min-long", "max-long"],
+        allow_hyphen_values = true,
+    )]
+    pub near: Option<Point>,
+
+    /// Search radius around --near point, e.g. 500m or 2km
+    #[clap(long, parse(try_from_str = parse_distance), requires = "near")]
+    pub radius: Option<f64>,
+
+    /// Maximum number of caches to request
+    #[clap(long, conflicts_with_all = &["near", "all"])]
+    pub limit: Option<usize>,
+
+    /// Number of matching caches to skip
+    #[clap(long, conflicts_with = "near")]
+    pub offset: Option<usize>,
+
+    /// Request all caches page by page, printing them as pages arrive
+    #[clap(long, conflicts_with_all = &["near", "sort-by", "reverse"])]
+    pub all: bool,
+
+    /// Number of caches per page with --all
+    #[clap(long, default_value = "100", requires = "all")]
+    pub page_size: usize,
+
+    #[clap(flatten)]
+    pub export: ExportArgs,
+
+    #[clap(flatten)]
+    pub table: TableArgs,
 }
 
-impl CacheFindArgsServer {
-    pub fn new(o: &CacheFindArgs) -> Self {
+impl From<&CacheFindArgs> for CacheFilter {
+    fn from(o: &CacheFindArgs) -> Self {
         Self {
             user_id: o.user,
             max_lat: o.max_lat,
             max_long: o.max_long,
             min_lat: o.min_lat,
             min_long: o.min_long,
+            limit: o.limit,
+            offset: o.offset,
         }
     }
 }
@@ -229,13 +661,53 @@ pub struct CacheViewArgs {
     /// ID of cache
     #[clap(short, long)]
     pub id: i32,
+
+    #[clap(flatten)]
+    pub export: ExportArgs,
 }
 
-#[derive(Args, Debug, Serialize)]
+/// Presentation of list results
+#[derive(Args, Debug)]
+pub struct TableArgs {
+    /// Comma separated columns of table, e.g. id,lat,long,descrip
+    #[clap(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Column to sort results by
+    #[clap(long)]
+    pub sort_by: Option<String>,
+
+    /// Reverse order of results
+    #[clap(long)]
+    pub reverse: bool,
+
+    /// Layout of text output
+    #[clap(long, arg_enum, default_value = "table")]
+    pub layout: Layout,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Export caches in file format instead of printing them
+    #[clap(long, arg_enum)]
+    pub export: Option<ExportFormat>,
+
+    /// File to export to. Stdout is used if not given
+    #[clap(long, requires = "export")]
+    pub export_file: Option<PathBuf>,
+}
+
+impl ExportArgs {
+    /// Returns `true` if export replaces printing of result
+    pub fn to_stdout(&self) -> bool {
+        self.export.is_some() && self.export_file.is_none()
+    }
+}
+
+#[derive(Args, Debug)]
 pub struct CacheChangeArgs {
     /// ID of cache
     #[clap(short, long)]
-    #[serde(skip_serializing)]
     pub id: i32,
 
     /// new latitide
@@ -260,3 +732,312 @@ pub struct CacheDeleteArgs {
     #[clap(short, long)]
     pub id: i32,
 }
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Operation on config
+    #[clap(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// List all profiles
+    List,
+    /// Add a new profile or replace existing one
+    Add(ConfigAddArgs),
+    /// Remove profile
+    Remove(ConfigRemoveArgs),
+    /// Show active profile
+    Show,
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigAddArgs {
+    /// Name of profile
+    pub name: String,
+
+    /// Base URL of API, e.g. http://127.0.0.1:8000/api/v1
+    #[clap(long)]
+    pub profile_base_url: Option<String>,
+
+    /// Api key to access server. Asked without echo if value is omitted. A value given here is visible in shell history
+    #[clap(long, min_values = 0, max_values = 1, value_name = "API_KEY")]
+    pub api_key: Option<Option<String>>,
+
+    /// Read api key from first line of stdin
+    #[clap(long, conflicts_with_all = &["api-key", "api-key-file"])]
+    pub api_key_stdin: bool,
+
+    /// Read api key from first line of file
+    #[clap(long, conflicts_with = "api-key")]
+    pub api_key_file: Option<PathBuf>,
+
+    /// Connect timeout in seconds
+    #[clap(long)]
+    pub profile_connect_timeout: Option<u64>,
+
+    /// Total request timeout in seconds
+    #[clap(long)]
+    pub profile_timeout: Option<u64>,
+
+    /// PEM bundle of CA certificates to trust
+    #[clap(long)]
+    pub profile_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate for mutual TLS
+    #[clap(long, requires = "profile-client-key")]
+    pub profile_client_cert: Option<PathBuf>,
+
+    /// PEM (PKCS#8) private key of client certificate
+    #[clap(long, requires = "profile-client-cert")]
+    pub profile_client_key: Option<PathBuf>,
+
+    /// Make this profile default
+    #[clap(long)]
+    pub default: bool,
+}
+
+impl ConfigAddArgs {
+    /// Reads api key from given source, `None` if no source is given
+    pub fn read_api_key(&self) -> Result<Option<String>, CliError> {
+        if let Some(path) = &self.api_key_file {
+            return secrets::read_file(path).map(Some);
+        }
+        if self.api_key_stdin {
+            return secrets::read_stdin().map(Some);
+        }
+
+        match &self.api_key {
+            Some(Some(k)) => Ok(Some(k.clone())),
+            Some(None) => secrets::prompt_api_key("--api-key-stdin").map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigRemoveArgs {
+    /// Name of profile
+    pub name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct CacheImportArgs {
+    /// GPX file with waypoints or CSV file with lat, long, descrip and hint columns
+    pub file: PathBuf,
+
+    /// Format of file. Detected by extension if not given
+    #[clap(long, arg_enum)]
+    pub format: Option<ImportFormat>,
+
+    /// CSV column name of cache field, e.g. --map descrip=Description
+    #[clap(
+        long = "map",
+        value_name = "FIELD=COLUMN",
+        parse(try_from_str = parse_mapping),
+        multiple_occurrences = true
+    )]
+    pub mappings: Vec<ColumnMapping>,
+
+    /// Stop at first failed row
+    #[clap(long)]
+    pub fail_fast: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ManifestArgs {
+    /// TOML manifest with a [caches.NAME] table of lat, long, descrip and hint per cache
+    #[clap(short = 'f', long)]
+    pub file: PathBuf,
+
+    /// JSON file with server ids of caches by name [default: NAME.state.json next to manifest]
+    #[clap(long)]
+    pub state: Option<PathBuf>,
+}
+
+impl ManifestArgs {
+    pub fn state_path(&self) -> PathBuf {
+        self.state
+            .clone()
+            .unwrap_or_else(|| State::default_path(&self.file))
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct CachePlanArgs {
+    #[clap(flatten)]
+    pub manifest: ManifestArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct CacheApplyArgs {
+    #[clap(flatten)]
+    pub manifest: ManifestArgs,
+
+    /// Apply plan without confirmation
+    #[clap(short, long)]
+    pub yes: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<MainCliArgs, clap::Error> {
+        MainCliArgs::try_parse_from(["msd-cli"].iter().chain(args))
+    }
+
+    fn password(args: &[&str]) -> PasswordArgs {
+        match parse(args).unwrap().command {
+            Command::User(UserArgs {
+                command: UserCommand::Change(change),
+            }) => change.password,
+            c => panic!("unexpected command {:?}", c),
+        }
+    }
+
+    #[test]
+    fn reads_password_from_given_source() {
+        let path = env::temp_dir().join(format!("msd-password-{}", std::process::id()));
+        fs::write(&path, "secret\n").unwrap();
+        let from_file = password(&[
+            "user",
+            "change",
+            "-i",
+            "1",
+            "--password-file",
+            path.to_str().unwrap(),
+        ])
+        .read(true);
+        fs::write(&path, "").unwrap();
+        let empty = password(&[
+            "user",
+            "change",
+            "-i",
+            "1",
+            "--password-file",
+            path.to_str().unwrap(),
+        ])
+        .read(true);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_file.unwrap().as_deref(), Some("secret"));
+        assert_eq!(empty.unwrap_err().exit_code(), 2);
+        assert_eq!(
+            password(&["user", "change", "-i", "1", "--password", "given"])
+                .read(true)
+                .unwrap()
+                .as_deref(),
+            Some("given")
+        );
+        // Password is optional for change
+        assert_eq!(
+            password(&["user", "change", "-i", "1"])
+                .read(false)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_secret_sources() {
+        for args in [
+            &[
+                "user",
+                "change",
+                "-i",
+                "1",
+                "--password",
+                "p",
+                "--password-stdin",
+            ][..],
+            &[
+                "user",
+                "change",
+                "-i",
+                "1",
+                "--password-stdin",
+                "--password-file",
+                "f",
+            ],
+            &[
+                "user",
+                "change",
+                "-i",
+                "1",
+                "--password",
+                "p",
+                "--password-file",
+                "f",
+            ],
+            &["--api", "k", "--api-file", "f", "user", "view", "-i", "1"],
+            &["config", "add", "p", "--api-key", "k", "--api-key-stdin"],
+            &[
+                "config",
+                "add",
+                "p",
+                "--api-key-stdin",
+                "--api-key-file",
+                "f",
+            ],
+            &[
+                "config",
+                "add",
+                "p",
+                "--api-key",
+                "k",
+                "--api-key-file",
+                "f",
+            ],
+        ] {
+            let e = parse(args).unwrap_err();
+            assert_eq!(e.kind(), clap::ErrorKind::ArgumentConflict, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn takes_api_key_by_precedence() {
+        let profile = Profile {
+            api_key: Some("from-profile".to_string()),
+            ..Profile::default()
+        };
+        let api_key = |args: &[&str]| {
+            let mut global = parse(args).unwrap().global;
+            global.apply_profile(&profile).map(|_| global.api)
+        };
+        let path = env::temp_dir().join(format!("msd-api-key-{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        let file = path.to_str().unwrap();
+
+        // No other test reads the variable, so it can be set here
+        env::set_var(API_KEY_ENV, "");
+        let empty_env = api_key(&["history"]);
+        env::set_var(API_KEY_ENV, "from-env");
+        let with_env = [
+            api_key(&["history"]),
+            api_key(&["--api-file", file, "history"]),
+            api_key(&["--api", "given", "history"]),
+        ];
+        env::remove_var(API_KEY_ENV);
+        let no_env = api_key(&["history"]);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(empty_env.unwrap().as_deref(), Some("from-profile"));
+        let [env_key, file_key, given] = with_env;
+        assert_eq!(env_key.unwrap().as_deref(), Some("from-env"));
+        assert_eq!(file_key.unwrap().as_deref(), Some("from-file"));
+        assert_eq!(given.unwrap().as_deref(), Some("given"));
+        assert_eq!(no_env.unwrap().as_deref(), Some("from-profile"));
+    }
+
+    #[test]
+    fn page_size_requires_all() {
+        let e = parse(&["cache", "find", "--page-size", "5"]).unwrap_err();
+        assert_eq!(e.kind(), clap::ErrorKind::MissingRequiredArgument);
+        assert!(parse(&["cache", "find", "--all", "--page-size", "5"]).is_ok());
+        assert!(parse(&["cache", "find"]).is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::secrets::write_private;
+
+static CONFIG_DIR_NAME: &str = "msd-cli";
+static CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Contents of the configuration file
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Config {
+    /// Profile used when `--profile` is not given
+    pub default_profile: Option<String>,
+
+    /// Named server profiles
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Connection settings of one server
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Profile {
+    /// Base URL of API, e.g. `https://127.0.0.1:8000/api/v1`
+    pub base_url: Option<String>,
+
+    /// Api key to access server
+    pub api_key: Option<String>,
+
+    /// Connect timeout in seconds
+    pub connect_timeout: Option<u64>,
+
+    /// Total request timeout in seconds
+    pub timeout: Option<u64>,
+
+    /// PEM bundle of CA certificates to trust
+    pub ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate for mutual TLS
+    pub client_cert: Option<PathBuf>,
+
+    /// PEM private key of client certificate
+    pub client_key: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(toml::ser::Error),
+    NoConfigDir,
+    ProfileNotFound(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Serialize(e) => write!(f, "Failed to serialize config: {}", e),
+            ConfigError::NoConfigDir => write!(f, "Unable to locate configuration directory"),
+            ConfigError::ProfileNotFound(name) => write!(f, "Profile '{}' not found", name),
+        }
+    }
+}
+
+impl Config {
+    /// Default config location: `$XDG_CONFIG_HOME/msd-cli/config.toml`
+    pub fn default_path() -> Result<PathBuf, ConfigError> {
+        dirs::config_dir()
+            .map(|d| d.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+            .ok_or(ConfigError::NoConfigDir)
+    }
+
+    /// Resolves config path from `--config` or the default location
+    pub fn path(custom: Option<&Path>) -> Result<PathBuf, ConfigError> {
+        match custom {
+            Some(p) => Ok(p.to_path_buf()),
+            None => Self::default_path(),
+        }
+    }
+
+    /// Loads config. A missing file gives an empty config.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ConfigError::Io(path.to_path_buf(), e)),
+        };
+
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let content = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| ConfigError::Io(dir.to_path_buf(), e))?;
+        }
+        // Profiles may have api keys
+        write_private(path, content.as_bytes()).map_err(|e| ConfigError::Io(path.to_path_buf(), e))
+    }
+
+    /// Name of active profile: explicitly selected one or default one
+    pub fn active_profile_name<'a>(&'a self, selected: Option<&'a str>) -> Option<&'a str> {
+        selected.or(self.default_profile.as_deref())
+    }
+
+    /// Returns active profile. No active profile gives empty profile.
+    pub fn active_profile(&self, selected: Option<&str>) -> Result<Profile, ConfigError> {
+        match self.active_profile_name(selected) {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ConfigError::ProfileNotFound(name.to_string())),
+            None => Ok(Profile::default()),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Client of server built from global options and active profile
+
+use std::{fs, path::Path, time::Duration};
+
+use msd_client::{MsdClient, MsdClientBuilder};
+
+use crate::{
+    cli::GlobalArgs, config::Config, credentials::Store, curl, error::CliError, trace::Tracer,
+};
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+
+/// Fills `global` from active profile and builds client. With `stored_key`
+/// the key stored by login is used when no other key is given.
+pub fn connect(global: &mut GlobalArgs, stored_key: bool) -> Result<MsdClient, CliError> {
+    let config = Config::load(&Config::path(global.config.as_deref())?)?;
+    let profile = config.active_profile(global.profile.as_deref())?;
+    global.apply_profile(&profile)?;
+    if stored_key && global.api.is_none() {
+        if let Some((credentials, _)) = Store::open(global)?.load(&global.get_api_base())? {
+            global.api = Some(credentials.api_key);
+        }
+    }
+    // Default profile is recorded in journal too
+    global.profile = config
+        .active_profile_name(global.profile.as_deref())
+        .map(str::to_string);
+
+    let mut client_builder = MsdClient::builder(global.get_api_base())
+        .user_agent(APP_USER_AGENT)
+        .connect_timeout(Duration::from_secs(global.connect_timeout.unwrap_or(5)))
+        .retry(global.retry_policy())
+        .dry_run(global.dry_run);
+
+    if let Some(k) = &global.api {
+        client_builder = client_builder.api_key(k);
+    }
+
+    if let Some(t) = global.timeout {
+        client_builder = client_builder.timeout(Duration::from_secs(t));
+    }
+
+    client_builder = configure_tls(client_builder, global)?;
+
+    if global.print_curl {
+        let secrets = global.curl_secrets;
+        client_builder = client_builder
+            .inspect_requests(move |request| eprintln!("{}", curl::command(request, secrets)));
+    }
+
+    if global.verbose > 0 || global.trace_file.is_some() {
+        let tracer = Tracer::new(global.verbose, global.trace_file.as_deref())?;
+        client_builder = client_builder.trace(move |event| tracer.event(event));
+    }
+
+    if let Some(dir) = &global.record {
+        client_builder = client_builder.record(dir);
+    }
+
+    if let Some(dir) = &global.replay {
+        client_builder = client_builder.replay(dir);
+    }
+
+    Ok(client_builder.build()?)
+}
+
+/// Client of command not talking to server. Profile is not applied, so
+/// `global` stays as given on command line.
+pub fn offline(global: &GlobalArgs) -> Result<MsdClient, CliError> {
+    Ok(MsdClient::builder(global.get_api_base())
+        .user_agent(APP_USER_AGENT)
+        .build()?)
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, CliError> {
+    fs::read(path).map_err(|e| CliError::Local(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+/// Applies CA bundle, client certificate and `--insecure` to client
+fn configure_tls(
+    mut builder: MsdClientBuilder,
+    global: &GlobalArgs,
+) -> Result<MsdClientBuilder, CliError> {
+    if let Some(path) = &global.ca_cert {
+        builder = builder.ca_certs_pem(read_pem(path)?);
+    }
+
+    if let (Some(cert), Some(key)) = (&global.client_cert, &global.client_key) {
+        builder = builder.client_identity_pem(read_pem(cert)?, read_pem(key)?);
+    }
+
+    if global.insecure {
+        eprintln!("WARNING: --insecure disables verification of server certificate!");
+        eprintln!("WARNING: Api key and data can be intercepted. Use it for testing only.");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder)
+}
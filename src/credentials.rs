@@ -0,0 +1,229 @@
+//! Api keys stored by `login`, one per server
+//!
+//! Keys are kept in the system secret store (Secret Service on Linux) through
+//! `secret-tool` of libsecret. When it is unavailable, or `--credentials` is
+//! given, keys are kept in a JSON file readable only by its owner. The file is
+//! not encrypted.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::GlobalArgs, error::CliError, secrets::write_private};
+
+static CREDENTIALS_DIR_NAME: &str = "msd-cli";
+static CREDENTIALS_FILE_NAME: &str = "credentials.json";
+
+/// Attribute of secrets in system secret store
+static KEYRING_SERVICE: &str = "msd-cli";
+
+/// Api key and its user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Credentials {
+    pub user_id: i32,
+    pub api_key: String,
+}
+
+/// Where credentials are kept
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreKind {
+    Keyring,
+    File,
+}
+
+pub struct Store {
+    file: PathBuf,
+    /// System secret store is tried before file
+    keyring: bool,
+}
+
+impl Store {
+    /// Store of `--credentials` file, or keyring with fallback to default file
+    pub fn open(global: &GlobalArgs) -> Result<Self, CliError> {
+        match &global.credentials {
+            Some(path) => Ok(Self {
+                file: path.clone(),
+                keyring: false,
+            }),
+            None => {
+                let file = dirs::data_dir()
+                    .map(|d| d.join(CREDENTIALS_DIR_NAME).join(CREDENTIALS_FILE_NAME))
+                    .ok_or_else(|| {
+                        CliError::Local(
+                            "Unable to locate data directory for credentials".to_string(),
+                        )
+                    })?;
+                Ok(Self {
+                    file,
+                    keyring: true,
+                })
+            }
+        }
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    fn error(&self, e: impl std::fmt::Display) -> CliError {
+        CliError::Local(format!("Credentials {}: {}", self.file.display(), e))
+    }
+
+    /// Credentials of server at `api_base`, if logged in
+    pub fn load(&self, api_base: &str) -> Result<Option<(Credentials, StoreKind)>, CliError> {
+        if self.keyring {
+            if let Some(secret) = keyring::lookup(api_base) {
+                let credentials = serde_json::from_str(&secret).map_err(|e| {
+                    CliError::Local(format!("Invalid credentials in keyring: {}", e))
+                })?;
+                return Ok(Some((credentials, StoreKind::Keyring)));
+            }
+        }
+
+        let mut all = self.read()?;
+        Ok(all.remove(api_base).map(|c| (c, StoreKind::File)))
+    }
+
+    /// Saves credentials of server at `api_base`, replacing previous ones
+    pub fn save(&self, api_base: &str, credentials: &Credentials) -> Result<StoreKind, CliError> {
+        let secret = serde_json::to_string(credentials).map_err(|e| self.error(e))?;
+        if self.keyring && keyring::store(api_base, &secret) {
+            // Key of earlier login without keyring must not be left behind
+            self.remove_from_file(api_base)?;
+            return Ok(StoreKind::Keyring);
+        }
+
+        let mut all = self.read()?;
+        all.insert(api_base.to_string(), credentials.clone());
+        self.write(&all)?;
+        Ok(StoreKind::File)
+    }
+
+    /// Removes credentials of server at `api_base`. Returns `false` if there were none.
+    pub fn remove(&self, api_base: &str) -> Result<bool, CliError> {
+        let in_keyring = self.keyring && keyring::lookup(api_base).is_some();
+        if in_keyring && !keyring::clear(api_base) {
+            return Err(CliError::Local(
+                "Failed to remove api key from keyring".to_string(),
+            ));
+        }
+
+        Ok(self.remove_from_file(api_base)? || in_keyring)
+    }
+
+    fn remove_from_file(&self, api_base: &str) -> Result<bool, CliError> {
+        let content = match self.content()? {
+            Some(c) => c,
+            None => return Ok(false),
+        };
+        let mut all: BTreeMap<String, Credentials> = match serde_json::from_str(&content) {
+            Ok(all) => all,
+            Err(e) => {
+                // Keys which can not be read are useless, logout gets rid of them
+                eprintln!("Warning: {}, removing it", self.error(e));
+                fs::remove_file(&self.file).map_err(|e| self.error(e))?;
+                return Ok(true);
+            }
+        };
+        if all.remove(api_base).is_none() {
+            return Ok(false);
+        }
+        self.write(&all)?;
+        Ok(true)
+    }
+
+    /// Content of file, `None` if it is missing
+    fn content(&self) -> Result<Option<String>, CliError> {
+        match fs::read_to_string(&self.file) {
+            Ok(c) => Ok(Some(c)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.error(e)),
+        }
+    }
+
+    /// Credentials in file by base URL. A missing file is empty.
+    fn read(&self) -> Result<BTreeMap<String, Credentials>, CliError> {
+        match self.content()? {
+            Some(content) => serde_json::from_str(&content).map_err(|e| {
+                CliError::Local(format!(
+                    "Credentials {}: {}, logout removes it",
+                    self.file.display(),
+                    e
+                ))
+            }),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn write(&self, all: &BTreeMap<String, Credentials>) -> Result<(), CliError> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| self.error(e))?;
+        }
+        let content = serde_json::to_string_pretty(all).map_err(|e| self.error(e))?;
+        write_private(&self.file, content.as_bytes()).map_err(|e| self.error(e))
+    }
+}
+
+/// System secret store used through `secret-tool`. Every function fails
+/// quietly when the tool or the store is unavailable.
+mod keyring {
+    use super::*;
+
+    fn secret_tool(args: &[&str]) -> Command {
+        let mut command = Command::new("secret-tool");
+        command.args(args).stderr(Stdio::null());
+        command
+    }
+
+    pub fn lookup(api_base: &str) -> Option<String> {
+        let output = secret_tool(&["lookup", "service", KEYRING_SERVICE, "server", api_base])
+            .stdin(Stdio::null())
+            .output()
+            .ok()?;
+        let secret = String::from_utf8(output.stdout).ok()?;
+        (output.status.success() && !secret.trim().is_empty()).then(|| secret.trim().to_string())
+    }
+
+    pub fn store(api_base: &str, secret: &str) -> bool {
+        let label = format!("msd-cli api key for {}", api_base);
+        let child = secret_tool(&[
+            "store",
+            "--label",
+            &label,
+            "service",
+            KEYRING_SERVICE,
+            "server",
+            api_base,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn();
+
+        let mut child = match child {
+            Ok(c) => c,
+            Err(_) => return false,
+        };
+        // Secret is read from stdin, so it is not visible in process list
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(secret.as_bytes()).is_ok());
+        let status = child.wait();
+        written && status.is_ok_and(|s| s.success())
+    }
+
+    pub fn clear(api_base: &str) -> bool {
+        secret_tool(&["clear", "service", KEYRING_SERVICE, "server", api_base])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+}
@@ -0,0 +1,89 @@
+//! Requests printed as curl commands for debugging
+
+use msd_client::{PreparedRequest, API_KEY_HEADER};
+
+use crate::secrets::{self, API_KEY_ENV};
+
+/// Copy-pasteable curl command sending `request`. Without `with_secrets` api key
+/// is taken from environment variable when command is run and passwords are redacted.
+pub fn command(request: &PreparedRequest, with_secrets: bool) -> String {
+    let method = match request.method.as_str() {
+        "GET" => String::new(),
+        m => format!("-X {} ", m),
+    };
+    let mut args = vec![format!("curl {}{}", method, quote(&request.url))];
+
+    for (name, value) in &request.headers {
+        let header = if name.eq_ignore_ascii_case(API_KEY_HEADER) && !with_secrets {
+            // Double quotes let shell expand variable
+            format!("\"{}: ${}\"", name, API_KEY_ENV)
+        } else {
+            quote(&format!("{}: {}", name, value))
+        };
+        args.push(format!("-H {}", header));
+    }
+
+    if let Some(body) = &request.body {
+        let body = if with_secrets {
+            body.clone()
+        } else {
+            secrets::redact(body)
+        };
+        args.push(format!("--data-raw {}", quote(&body.to_string())));
+    }
+
+    args.join(" \\\n  ")
+}
+
+fn quote(s: &str) -> String {
+    shell_words::quote(s).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> PreparedRequest {
+        PreparedRequest {
+            method: "POST".to_string(),
+            url: "http://127.0.0.1:8000/api/v1/cache/".to_string(),
+            headers: vec![
+                ("x-api-key".to_string(), "secret".to_string()),
+                ("content-type".to_string(), "application/json".to_string()),
+            ],
+            body: Some(json!({ "descrip": "Rock's top", "password": "pw" })),
+        }
+    }
+
+    #[test]
+    fn quotes_arguments() {
+        assert_eq!(
+            command(&request(), false),
+            "curl -X POST http://127.0.0.1:8000/api/v1/cache/ \\\n  \
+             -H \"x-api-key: $MSD_API_KEY\" \\\n  \
+             -H 'content-type: application/json' \\\n  \
+             --data-raw '{\"descrip\":\"Rock'\\''s top\",\"password\":\"<redacted>\"}'"
+        );
+    }
+
+    #[test]
+    fn shows_secrets_on_request() {
+        let command = command(&request(), true);
+        assert!(command.contains("-H 'x-api-key: secret'"));
+        assert!(command.contains(r#""password":"pw""#));
+    }
+
+    #[test]
+    fn get_has_query_and_no_method() {
+        let request = PreparedRequest {
+            method: "GET".to_string(),
+            url: "http://127.0.0.1:8000/api/v1/cache/?user_id=3&limit=10".to_string(),
+            headers: Vec::new(),
+            body: None,
+        };
+        assert_eq!(
+            command(&request, false),
+            "curl 'http://127.0.0.1:8000/api/v1/cache/?user_id=3&limit=10'"
+        );
+    }
+}
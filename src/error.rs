@@ -0,0 +1,92 @@
+//! Errors of application and their exit codes
+//!
+//! | Code | Meaning                                          |
+//! |------|--------------------------------------------------|
+//! | 0    | Success                                          |
+//! | 1    | Local failure (file system, etc.)                |
+//! | 2    | Usage error: invalid arguments or configuration  |
+//! | 3    | Transport error: failed to connect or send       |
+//! | 4    | Request timed out                                |
+//! | 5    | Server answered with unsuccessful HTTP status    |
+//! | 6    | Server reported an error (`{"error": true}`)     |
+//! | 7    | Malformed server response                        |
+//! | 8    | Some items of bulk operation failed              |
+//! | 9    | Request has no response in replayed cassette     |
+
+use std::fmt;
+
+use msd_client::ClientError;
+
+use crate::config::ConfigError;
+
+/// Help text describing exit codes
+pub static EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    Success
+    1    Local failure (file system, etc.)
+    2    Usage error: invalid arguments or configuration
+    3    Transport error: failed to connect or send request
+    4    Request timed out
+    5    Server answered with unsuccessful HTTP status
+    6    Server reported an error
+    7    Malformed server response
+    8    Some items of bulk operation failed
+    9    Request has no response in replayed cassette";
+
+#[derive(Debug)]
+pub enum CliError {
+    /// Request to server failed
+    Client(ClientError),
+    /// Invalid command line arguments or configuration
+    Usage(String),
+    /// Local failure: file system, etc.
+    Local(String),
+    /// Some items of bulk operation failed, details are already reported
+    Partial(String),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Local(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::Partial(_) => 8,
+            CliError::Client(e) => match e {
+                ClientError::InvalidConfig(_) => 2,
+                ClientError::Transport(_) => 3,
+                ClientError::Timeout(_) => 4,
+                ClientError::HttpStatus(_) => 5,
+                ClientError::Server { .. } => 6,
+                ClientError::MalformedResponse(_) => 7,
+                ClientError::Cassette(_) => 9,
+            },
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Client(e) => write!(f, "{}", e),
+            CliError::Usage(msg) => write!(f, "{}", msg),
+            CliError::Local(msg) => write!(f, "{}", msg),
+            CliError::Partial(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<ClientError> for CliError {
+    fn from(e: ClientError) -> Self {
+        CliError::Client(e)
+    }
+}
+
+impl From<ConfigError> for CliError {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Parse(..) | ConfigError::ProfileNotFound(_) => {
+                CliError::Usage(format!("Config error: {}", e))
+            }
+            _ => CliError::Local(format!("Config error: {}", e)),
+        }
+    }
+}
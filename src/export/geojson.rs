@@ -0,0 +1,66 @@
+//! GeoJSON (RFC 7946) export. Caches are `Point` features of a
+//! `FeatureCollection` with id, descrip, hint and owner properties.
+
+use std::io::{self, Write};
+
+use msd_client::Cache;
+use serde_json::Value;
+
+fn feature(cache: &Cache) -> Value {
+    json!({
+        "type": "Feature",
+        "id": cache.id,
+        "geometry": {
+            "type": "Point",
+            // GeoJSON positions are longitude first
+            "coordinates": [cache.long, cache.lat],
+        },
+        "properties": {
+            "id": cache.id,
+            "descrip": cache.descrip,
+            "hint": cache.hint,
+            "owner": cache.owner,
+        },
+    })
+}
+
+pub fn write_geojson<W: Write>(mut out: W, caches: &[Cache]) -> io::Result<()> {
+    let collection = json!({
+        "type": "FeatureCollection",
+        "features": caches.iter().map(feature).collect::<Vec<_>>(),
+    });
+
+    serde_json::to_writer_pretty(&mut out, &collection)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_collection() {
+        let caches = vec![Cache {
+            id: 7,
+            owner: Some(2),
+            lat: 55.75,
+            long: 37.61,
+            descrip: "Red square".to_string(),
+            hint: "near wall".to_string(),
+        }];
+
+        let mut out = Vec::new();
+        write_geojson(&mut out, &caches).unwrap();
+        let doc: Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(doc["type"], "FeatureCollection");
+        let feature = &doc["features"][0];
+        assert_eq!(feature["type"], "Feature");
+        assert_eq!(feature["geometry"]["type"], "Point");
+        assert_eq!(feature["geometry"]["coordinates"], json!([37.61, 55.75]));
+        assert_eq!(feature["properties"]["id"], 7);
+        assert_eq!(feature["properties"]["descrip"], "Red square");
+        assert_eq!(feature["properties"]["hint"], "near wall");
+        assert_eq!(feature["properties"]["owner"], 2);
+    }
+}
@@ -0,0 +1,185 @@
+//! GPX 1.1 export. Every cache is a `<wpt>` with id as name, description as
+//! `<desc>` and hint in `<msd:hint>` extension element.
+
+use std::io::{self, Write};
+
+use msd_client::Cache;
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+
+pub static GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+pub static MSD_NAMESPACE: &str = "https://github.com/Deka-Labs/msd/gpx/1";
+
+static GPX_SCHEMA_LOCATION: &str =
+    "http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/1/1/gpx.xsd";
+static CREATOR: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+
+fn xml_error(e: quick_xml::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+pub fn write_gpx<W: Write>(out: W, caches: &[Cache]) -> io::Result<()> {
+    let mut writer = Writer::new_with_indent(out, b' ', 2);
+
+    writer
+        .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
+        .map_err(xml_error)?;
+
+    writer
+        .create_element("gpx")
+        .with_attributes([
+            ("version", "1.1"),
+            ("creator", CREATOR),
+            ("xmlns", GPX_NAMESPACE),
+            ("xmlns:msd", MSD_NAMESPACE),
+            ("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"),
+            ("xsi:schemaLocation", GPX_SCHEMA_LOCATION),
+        ])
+        .write_inner_content(|w| {
+            for cache in caches {
+                write_waypoint(w, cache)?;
+            }
+            Ok::<(), quick_xml::Error>(())
+        })
+        .map_err(xml_error)?;
+
+    writeln!(writer.into_inner())
+}
+
+fn write_waypoint<W: Write>(w: &mut Writer<W>, cache: &Cache) -> quick_xml::Result<()> {
+    w.create_element("wpt")
+        .with_attribute(("lat", cache.lat.to_string().as_str()))
+        .with_attribute(("lon", cache.long.to_string().as_str()))
+        .write_inner_content(|w| {
+            w.create_element("name")
+                .write_text_content(BytesText::new(&cache.id.to_string()))?;
+            w.create_element("desc")
+                .write_text_content(BytesText::new(&cache.descrip))?;
+            w.create_element("sym")
+                .write_text_content(BytesText::new("Geocache"))?;
+            w.create_element("type")
+                .write_text_content(BytesText::new("Geocache"))?;
+            w.create_element("extensions").write_inner_content(|w| {
+                w.create_element("msd:hint")
+                    .write_text_content(BytesText::new(&cache.hint))?;
+                if let Some(owner) = cache.owner {
+                    w.create_element("msd:owner")
+                        .write_text_content(BytesText::new(&owner.to_string()))?;
+                }
+                Ok::<(), quick_xml::Error>(())
+            })?;
+            Ok::<(), quick_xml::Error>(())
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::{
+        events::Event,
+        name::{Namespace, ResolveResult},
+        NsReader,
+    };
+
+    use super::*;
+
+    /// Reads waypoints back with namespace aware XML parser
+    fn parse_gpx(xml: &str) -> Vec<Cache> {
+        let mut reader = NsReader::from_str(xml);
+        let mut caches = Vec::new();
+        let mut field: Option<(Vec<u8>, Vec<u8>)> = None;
+
+        loop {
+            match reader.read_resolved_event().unwrap() {
+                (ResolveResult::Bound(Namespace(ns)), Event::Start(e)) => {
+                    let name = e.local_name().as_ref().to_vec();
+                    if ns == GPX_NAMESPACE.as_bytes() && name == b"wpt" {
+                        let attr = |n: &str| -> f64 {
+                            let a = e.try_get_attribute(n).unwrap().unwrap();
+                            a.unescape_value().unwrap().parse().unwrap()
+                        };
+                        caches.push(Cache {
+                            id: 0,
+                            owner: None,
+                            lat: attr("lat"),
+                            long: attr("lon"),
+                            descrip: String::new(),
+                            hint: String::new(),
+                        });
+                    }
+                    field = Some((ns.to_vec(), name));
+                }
+                (_, Event::Text(t)) => {
+                    let text = t.unescape().unwrap().to_string();
+                    let cache = caches.last_mut();
+                    match (field.as_ref(), cache) {
+                        (Some((ns, name)), Some(c)) if ns == GPX_NAMESPACE.as_bytes() => {
+                            match name.as_slice() {
+                                b"name" => c.id = text.parse().unwrap(),
+                                b"desc" => c.descrip = text,
+                                _ => {}
+                            }
+                        }
+                        (Some((ns, name)), Some(c)) if ns == MSD_NAMESPACE.as_bytes() => {
+                            match name.as_slice() {
+                                b"hint" => c.hint = text,
+                                b"owner" => c.owner = Some(text.parse().unwrap()),
+                                _ => {}
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                (_, Event::End(_)) => field = None,
+                (_, Event::Eof) => break,
+                _ => {}
+            }
+        }
+
+        caches
+    }
+
+    fn write_to_string(caches: &[Cache]) -> String {
+        let mut out = Vec::new();
+        write_gpx(&mut out, caches).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let caches = vec![
+            Cache {
+                id: 1,
+                owner: Some(3),
+                lat: 55.7539,
+                long: 37.6208,
+                descrip: "Red square".to_string(),
+                hint: "Near <the> wall & \"tower\"".to_string(),
+            },
+            Cache {
+                id: 42,
+                owner: None,
+                lat: -33.8568,
+                long: 151.2153,
+                descrip: "Opera & harbour".to_string(),
+                hint: "Under bench".to_string(),
+            },
+        ];
+
+        let xml = write_to_string(&caches);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(xml.contains("version=\"1.1\""));
+        assert_eq!(parse_gpx(&xml), caches);
+    }
+
+    #[test]
+    fn empty_document() {
+        let xml = write_to_string(&[]);
+
+        assert!(xml.contains("<gpx"));
+        assert!(parse_gpx(&xml).is_empty());
+    }
+}
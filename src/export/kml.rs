@@ -0,0 +1,125 @@
+//! KML 2.2 export. Every cache is a `<Placemark>` with id as name, description
+//! and hint and owner in `<ExtendedData>`.
+
+use std::io::{self, Write};
+
+use msd_client::Cache;
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+
+pub static KML_NAMESPACE: &str = "http://www.opengis.net/kml/2.2";
+
+fn xml_error(e: quick_xml::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+pub fn write_kml<W: Write>(out: W, caches: &[Cache]) -> io::Result<()> {
+    let mut writer = Writer::new_with_indent(out, b' ', 2);
+
+    writer
+        .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
+        .map_err(xml_error)?;
+
+    writer
+        .create_element("kml")
+        .with_attribute(("xmlns", KML_NAMESPACE))
+        .write_inner_content(|w| {
+            w.create_element("Document").write_inner_content(|w| {
+                w.create_element("name")
+                    .write_text_content(BytesText::new("Caches"))?;
+                for cache in caches {
+                    write_placemark(w, cache)?;
+                }
+                Ok::<(), quick_xml::Error>(())
+            })?;
+            Ok::<(), quick_xml::Error>(())
+        })
+        .map_err(xml_error)?;
+
+    writeln!(writer.into_inner())
+}
+
+fn write_data<W: Write>(w: &mut Writer<W>, name: &str, value: &str) -> quick_xml::Result<()> {
+    w.create_element("Data")
+        .with_attribute(("name", name))
+        .write_inner_content(|w| {
+            w.create_element("value")
+                .write_text_content(BytesText::new(value))?;
+            Ok::<(), quick_xml::Error>(())
+        })?;
+    Ok(())
+}
+
+fn write_placemark<W: Write>(w: &mut Writer<W>, cache: &Cache) -> quick_xml::Result<()> {
+    // XML ID can not start with digit
+    let id = format!("cache-{}", cache.id);
+
+    w.create_element("Placemark")
+        .with_attribute(("id", id.as_str()))
+        .write_inner_content(|w| {
+            w.create_element("name")
+                .write_text_content(BytesText::new(&cache.id.to_string()))?;
+            w.create_element("description")
+                .write_text_content(BytesText::new(&cache.descrip))?;
+            w.create_element("ExtendedData").write_inner_content(|w| {
+                write_data(w, "hint", &cache.hint)?;
+                if let Some(owner) = cache.owner {
+                    write_data(w, "owner", &owner.to_string())?;
+                }
+                Ok::<(), quick_xml::Error>(())
+            })?;
+            w.create_element("Point").write_inner_content(|w| {
+                let coordinates = format!("{},{}", cache.long, cache.lat);
+                w.create_element("coordinates")
+                    .write_text_content(BytesText::new(&coordinates))?;
+                Ok::<(), quick_xml::Error>(())
+            })?;
+            Ok::<(), quick_xml::Error>(())
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::{events::Event, Reader};
+
+    use super::*;
+
+    #[test]
+    fn placemarks() {
+        let caches = vec![Cache {
+            id: 7,
+            owner: None,
+            lat: 55.75,
+            long: 37.61,
+            descrip: "Red <square>".to_string(),
+            hint: "near wall".to_string(),
+        }];
+
+        let mut out = Vec::new();
+        write_kml(&mut out, &caches).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+
+        let mut reader = Reader::from_str(&xml);
+        reader.trim_text(true);
+        let mut texts = Vec::new();
+        let mut placemarks = 0;
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) if e.name().as_ref() == b"Placemark" => placemarks += 1,
+                Event::Text(t) => texts.push(t.unescape().unwrap().to_string()),
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        assert!(xml.contains(&format!("<kml xmlns=\"{}\">", KML_NAMESPACE)));
+        assert_eq!(placemarks, 1);
+        assert_eq!(
+            texts,
+            ["Caches", "7", "Red <square>", "near wall", "37.61,55.75"]
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Export of caches to files for GPS units and GIS tools
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use clap::ArgEnum;
+use msd_client::Cache;
+
+use crate::error::CliError;
+
+mod geojson;
+mod gpx;
+mod kml;
+
+/// File format of exported caches
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// GPX 1.1 waypoints
+    Gpx,
+    /// GeoJSON FeatureCollection of points
+    Geojson,
+    /// KML 2.2 document with placemarks
+    Kml,
+}
+
+fn write_caches<W: Write>(out: W, format: ExportFormat, caches: &[Cache]) -> io::Result<()> {
+    match format {
+        ExportFormat::Gpx => gpx::write_gpx(out, caches),
+        ExportFormat::Geojson => geojson::write_geojson(out, caches),
+        ExportFormat::Kml => kml::write_kml(out, caches),
+    }
+}
+
+/// Writes caches to file or to stdout if `path` is `None`
+pub fn export_caches(
+    format: ExportFormat,
+    path: Option<&Path>,
+    caches: &[Cache],
+) -> Result<(), CliError> {
+    let local_error = |e: io::Error| match path {
+        Some(p) => CliError::Local(format!("Failed to export to {}: {}", p.display(), e)),
+        None => CliError::Local(format!("Failed to export: {}", e)),
+    };
+
+    match path {
+        Some(p) => {
+            let mut out = BufWriter::new(File::create(p).map_err(local_error)?);
+            write_caches(&mut out, format, caches).map_err(local_error)?;
+            out.flush().map_err(local_error)
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            write_caches(&mut out, format, caches).map_err(local_error)?;
+            out.flush().map_err(local_error)
+        }
+    }
+}
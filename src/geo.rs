@@ -0,0 +1,284 @@
+//! Geodesic helpers for searching caches around a point
+
+use std::f64::consts::PI;
+
+/// Mean Earth radius in meters
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub lat: f64,
+    pub long: f64,
+}
+
+/// Rectangular area in degrees. `min_long <= max_long` always holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_long: f64,
+    pub max_long: f64,
+}
+
+/// Parses point in `LAT,LONG` format
+pub fn parse_point(s: &str) -> Result<Point, String> {
+    let (lat, long) = s
+        .split_once(',')
+        .ok_or_else(|| "expected LAT,LONG".to_string())?;
+
+    let lat: f64 = lat
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid latitude: {}", lat))?;
+    let long: f64 = long
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid longitude: {}", long))?;
+
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err("latitude must be in [-90, 90]".to_string());
+    }
+    if !(-180.0..=180.0).contains(&long) {
+        return Err("longitude must be in [-180, 180]".to_string());
+    }
+
+    Ok(Point { lat, long })
+}
+
+/// Parses distance like `500`, `500m` or `2km` into meters
+pub fn parse_distance(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let (number, factor) = if let Some(n) = s.strip_suffix("km") {
+        (n, 1000.0)
+    } else if let Some(n) = s.strip_suffix('m') {
+        (n, 1.0)
+    } else {
+        (s, 1.0)
+    };
+
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid distance: {}", s))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err("distance must be positive".to_string());
+    }
+
+    Ok(value * factor)
+}
+
+/// Great-circle distance in meters (haversine formula)
+pub fn distance(a: Point, b: Point) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_long = (b.long - a.long).to_radians();
+
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_long / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+/// Initial bearing from `a` to `b` in degrees, clockwise from north in [0, 360)
+pub fn bearing(a: Point, b: Point) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_long = (b.long - a.long).to_radians();
+
+    let y = d_long.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_long.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// Compass direction of bearing, e.g. `NE`
+pub fn compass_point(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    POINTS[((bearing + 22.5) / 45.0) as usize % 8]
+}
+
+/// Boxes covering circle of `radius` meters around `center`.
+///
+/// Returns two boxes when the circle crosses the antimeridian. A circle
+/// containing a pole gives a box spanning all longitudes.
+pub fn bounding_boxes(center: Point, radius: f64) -> Vec<BoundingBox> {
+    let r = radius / EARTH_RADIUS;
+    let lat = center.lat.to_radians();
+    let long = center.long.to_radians();
+
+    let min_lat = lat - r;
+    let max_lat = lat + r;
+
+    if r >= PI || min_lat <= -PI / 2.0 || max_lat >= PI / 2.0 {
+        return vec![BoundingBox {
+            min_lat: min_lat.max(-PI / 2.0).to_degrees(),
+            max_lat: max_lat.min(PI / 2.0).to_degrees(),
+            min_long: -180.0,
+            max_long: 180.0,
+        }];
+    }
+
+    let d_long = (r.sin() / lat.cos()).asin();
+    let min_long = (long - d_long).to_degrees();
+    let max_long = (long + d_long).to_degrees();
+    let (min_lat, max_lat) = (min_lat.to_degrees(), max_lat.to_degrees());
+
+    let make = |min_long: f64, max_long: f64| BoundingBox {
+        min_lat,
+        max_lat,
+        min_long,
+        max_long,
+    };
+
+    if min_long < -180.0 {
+        vec![make(min_long + 360.0, 180.0), make(-180.0, max_long)]
+    } else if max_long > 180.0 {
+        vec![make(min_long, 180.0), make(-180.0, max_long - 360.0)]
+    } else {
+        vec![make(min_long, max_long)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARIS: Point = Point {
+        lat: 48.8566,
+        long: 2.3522,
+    };
+    const LONDON: Point = Point {
+        lat: 51.5074,
+        long: -0.1278,
+    };
+
+    fn near(a: f64, b: f64, epsilon: f64) -> bool {
+        (a - b).abs() < epsilon
+    }
+
+    #[test]
+    fn parses_points() {
+        assert_eq!(
+            parse_point(" -33.9 , 151.2 "),
+            Ok(Point {
+                lat: -33.9,
+                long: 151.2
+            })
+        );
+        assert!(parse_point("55.75").is_err());
+        assert!(parse_point("north,37.6").is_err());
+        assert!(parse_point("55.75,").is_err());
+        assert!(parse_point("90.1,0").is_err());
+        assert!(parse_point("0,-180.1").is_err());
+    }
+
+    #[test]
+    fn parses_distances() {
+        assert_eq!(parse_distance("500"), Ok(500.0));
+        assert_eq!(parse_distance("500m"), Ok(500.0));
+        assert_eq!(parse_distance("1.5 km"), Ok(1500.0));
+        assert!(parse_distance("-2km").is_err());
+        assert!(parse_distance("0").is_err());
+        assert!(parse_distance("km").is_err());
+        assert!(parse_distance("2mi").is_err());
+        assert!(parse_distance("inf").is_err());
+        assert!(parse_distance("NaN").is_err());
+    }
+
+    #[test]
+    fn measures_distance_and_bearing() {
+        assert!(near(distance(PARIS, LONDON), 343_556.5, 1.0));
+        assert!(near(distance(LONDON, PARIS), 343_556.5, 1.0));
+        assert_eq!(distance(PARIS, PARIS), 0.0);
+        // Degree of equator
+        let origin = Point {
+            lat: 0.0,
+            long: 0.0,
+        };
+        let east = Point {
+            lat: 0.0,
+            long: 1.0,
+        };
+        assert!(near(distance(origin, east), 111_195.1, 1.0));
+
+        assert!(near(bearing(PARIS, LONDON), 330.02, 0.01));
+        assert!(near(bearing(LONDON, PARIS), 148.12, 0.01));
+        assert!(near(bearing(origin, east), 90.0, 1e-9));
+        assert!(near(bearing(east, origin), 270.0, 1e-9));
+        assert!(near(
+            bearing(
+                origin,
+                Point {
+                    lat: 1.0,
+                    long: 0.0
+                }
+            ),
+            0.0,
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn names_compass_points() {
+        assert_eq!(compass_point(0.0), "N");
+        assert_eq!(compass_point(22.4), "N");
+        assert_eq!(compass_point(22.5), "NE");
+        assert_eq!(compass_point(330.02), "NW");
+        assert_eq!(compass_point(359.9), "N");
+    }
+
+    #[test]
+    fn splits_boxes_at_antimeridian() {
+        let boxes = bounding_boxes(
+            Point {
+                lat: 0.0,
+                long: 179.9,
+            },
+            50_000.0,
+        );
+        assert_eq!(boxes.len(), 2);
+        assert!(near(boxes[0].min_long, 179.4503, 1e-4));
+        assert_eq!(boxes[0].max_long, 180.0);
+        assert_eq!(boxes[1].min_long, -180.0);
+        assert!(near(boxes[1].max_long, -179.6503, 1e-4));
+        for b in &boxes {
+            assert!(near(b.min_lat, -0.4497, 1e-4));
+            assert!(near(b.max_lat, 0.4497, 1e-4));
+        }
+
+        let boxes = bounding_boxes(
+            Point {
+                lat: 0.0,
+                long: -179.9,
+            },
+            50_000.0,
+        );
+        assert_eq!(boxes.len(), 2);
+        assert!(near(boxes[0].min_long, 179.6503, 1e-4));
+        assert!(near(boxes[1].max_long, -179.4503, 1e-4));
+
+        assert_eq!(bounding_boxes(PARIS, 50_000.0).len(), 1);
+    }
+
+    #[test]
+    fn covers_pole_with_all_longitudes() {
+        let boxes = bounding_boxes(
+            Point {
+                lat: 89.9,
+                long: 10.0,
+            },
+            50_000.0,
+        );
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].max_lat, 90.0);
+        assert!(near(boxes[0].min_lat, 89.4503, 1e-4));
+        assert_eq!((boxes[0].min_long, boxes[0].max_long), (-180.0, 180.0));
+
+        let boxes = bounding_boxes(PARIS, 30_000_000.0);
+        assert_eq!(
+            boxes,
+            [BoundingBox {
+                min_lat: -90.0,
+                max_lat: 90.0,
+                min_long: -180.0,
+                max_long: 180.0,
+            }]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use msd_client::NewCache;
+
+use super::{validate, ImportRow};
+
+static FIELDS: [&str; 4] = ["lat", "long", "descrip", "hint"];
+
+/// Maps cache field to CSV column name
+pub type ColumnMapping = (String, String);
+
+/// Parses mapping in `FIELD=COLUMN` format
+pub fn parse_mapping(s: &str) -> Result<ColumnMapping, String> {
+    let (field, column) = s
+        .split_once('=')
+        .ok_or_else(|| "expected FIELD=COLUMN".to_string())?;
+
+    if !FIELDS.contains(&field) {
+        return Err(format!(
+            "unknown field {}, expected one of: {}",
+            field,
+            FIELDS.join(", ")
+        ));
+    }
+
+    Ok((field.to_string(), column.to_string()))
+}
+
+pub fn read_rows(content: &str, mappings: &[ColumnMapping]) -> Result<Vec<ImportRow>, String> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .trim(::csv::Trim::All)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+
+    // Index of column for every field in order of FIELDS
+    let mut columns = [0; 4];
+    for (i, field) in FIELDS.iter().enumerate() {
+        let column = mappings
+            .iter()
+            .rev()
+            .find(|(f, _)| f == field)
+            .map_or(*field, |(_, c)| c.as_str());
+
+        columns[i] = headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(column))
+            .ok_or_else(|| format!("no column {} for field {}", column, field))?;
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let row = record.position().map_or(0, |p| p.line() as usize);
+
+        let value = |i: usize| record.get(columns[i]).unwrap_or_default();
+        let number = |i: usize| {
+            value(i)
+                .parse::<f64>()
+                .map_err(|_| format!("invalid {}: '{}'", FIELDS[i], value(i)))
+        };
+
+        let cache = number(0).and_then(|lat| {
+            validate(NewCache {
+                lat,
+                long: number(1)?,
+                descrip: value(2).to_string(),
+                hint: value(3).to_string(),
+            })
+        });
+
+        rows.push(ImportRow { row, cache });
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_columns() {
+        let content = "Hint,Latitude,long,Description\nunder bench,1.5,2.5,Park\nx,abc,2,Bad\n";
+        let mappings = vec![
+            parse_mapping("lat=latitude").unwrap(),
+            parse_mapping("descrip=Description").unwrap(),
+        ];
+
+        let rows = read_rows(content, &mappings).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].row, 2);
+        let cache = rows[0].cache.as_ref().unwrap();
+        assert_eq!((cache.lat, cache.long), (1.5, 2.5));
+        assert_eq!(cache.descrip, "Park");
+        assert_eq!(cache.hint, "under bench");
+        assert!(rows[1].cache.is_err());
+
+        assert!(read_rows(content, &[]).is_err());
+        assert!(parse_mapping("owner=Owner").is_err());
+    }
+}
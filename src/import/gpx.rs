@@ -0,0 +1,149 @@
+use msd_client::NewCache;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use super::{validate, ImportRow};
+
+/// Waypoint fields collected while reading
+#[derive(Default)]
+struct Waypoint {
+    lat: Option<String>,
+    lon: Option<String>,
+    name: Option<String>,
+    desc: Option<String>,
+    cmt: Option<String>,
+    hint: Option<String>,
+}
+
+impl Waypoint {
+    fn from_attributes(e: &BytesStart) -> Result<Self, String> {
+        let mut wpt = Waypoint::default();
+        for attr in e.attributes() {
+            let attr = attr.map_err(|e| e.to_string())?;
+            let value = attr.unescape_value().map_err(|e| e.to_string())?;
+            match attr.key.local_name().as_ref() {
+                b"lat" => wpt.lat = Some(value.to_string()),
+                b"lon" => wpt.lon = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Ok(wpt)
+    }
+
+    fn set_field(&mut self, element: &[u8], text: String) {
+        match element {
+            b"name" => self.name = Some(text),
+            b"desc" => self.desc = Some(text),
+            b"cmt" => self.cmt = Some(text),
+            b"hint" => self.hint = Some(text),
+            _ => {}
+        }
+    }
+
+    /// Description is taken from `desc` or `name`, hint from `hint` extension or `cmt`
+    fn into_cache(self) -> Result<NewCache, String> {
+        let coord = |v: Option<String>, name: &str| {
+            let v = v.ok_or_else(|| format!("missing {} attribute", name))?;
+            v.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid {}: '{}'", name, v))
+        };
+
+        validate(NewCache {
+            lat: coord(self.lat, "lat")?,
+            long: coord(self.lon, "lon")?,
+            descrip: self.desc.or(self.name).unwrap_or_default(),
+            hint: self.hint.or(self.cmt).unwrap_or_default(),
+        })
+    }
+}
+
+/// Reads `<wpt>` elements of GPX 1.0 or 1.1 document
+pub fn read_waypoints(content: &str) -> Result<Vec<ImportRow>, String> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut rows = Vec::new();
+    let mut waypoint: Option<Waypoint> = None;
+    let mut element = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("invalid XML at {}: {}", reader.buffer_position(), e))?;
+
+        match event {
+            Event::Start(e) if e.local_name().as_ref() == b"wpt" => {
+                waypoint = Some(Waypoint::from_attributes(&e)?);
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"wpt" => {
+                let wpt = Waypoint::from_attributes(&e)?;
+                rows.push(ImportRow {
+                    row: rows.len() + 1,
+                    cache: wpt.into_cache(),
+                });
+            }
+            Event::Start(e) => element = e.local_name().as_ref().to_vec(),
+            Event::Text(t) => {
+                if let Some(wpt) = waypoint.as_mut() {
+                    let text = t.unescape().map_err(|e| e.to_string())?;
+                    wpt.set_field(&element, text.to_string());
+                }
+            }
+            Event::CData(c) => {
+                if let Some(wpt) = waypoint.as_mut() {
+                    wpt.set_field(&element, String::from_utf8_lossy(&c).to_string());
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"wpt" => {
+                if let Some(wpt) = waypoint.take() {
+                    rows.push(ImportRow {
+                        row: rows.len() + 1,
+                        cache: wpt.into_cache(),
+                    });
+                }
+            }
+            Event::End(_) => element.clear(),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_waypoints() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1" xmlns:msd="https://github.com/Deka-Labs/msd/gpx/1">
+  <wpt lat="55.75" lon="37.61">
+    <name>1</name>
+    <desc>Red &amp; square</desc>
+    <extensions><msd:hint>near wall</msd:hint></extensions>
+  </wpt>
+  <wpt lat="10" lon="20"><name>Only name</name><cmt><![CDATA[in <cdata>]]></cmt></wpt>
+  <wpt lat="95" lon="20"><desc>Bad</desc></wpt>
+</gpx>"#;
+
+        let rows = read_waypoints(xml).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        let first = rows[0].cache.as_ref().unwrap();
+        assert_eq!((first.lat, first.long), (55.75, 37.61));
+        assert_eq!(first.descrip, "Red & square");
+        assert_eq!(first.hint, "near wall");
+
+        let second = rows[1].cache.as_ref().unwrap();
+        assert_eq!(second.descrip, "Only name");
+        assert_eq!(second.hint, "in <cdata>");
+
+        assert_eq!(rows[2].row, 3);
+        assert!(rows[2].cache.is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Reading caches to create from GPX and CSV files
+
+use std::{fs, path::Path};
+
+use clap::ArgEnum;
+use msd_client::NewCache;
+
+use crate::error::CliError;
+
+mod csv;
+mod gpx;
+
+pub use self::csv::{parse_mapping, ColumnMapping};
+
+/// File format of imported caches
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// GPX waypoints
+    Gpx,
+    /// CSV with header
+    Csv,
+}
+
+impl ImportFormat {
+    /// Detects format by file extension
+    pub fn detect(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gpx" => Some(ImportFormat::Gpx),
+            "csv" => Some(ImportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// One entry of imported file
+#[derive(Debug)]
+pub struct ImportRow {
+    /// Line number in CSV file or waypoint number in GPX file
+    pub row: usize,
+    /// Cache to create or validation error
+    pub cache: Result<NewCache, String>,
+}
+
+/// Reads and validates all entries of file
+pub fn read_file(
+    path: &Path,
+    format: ImportFormat,
+    mappings: &[ColumnMapping],
+) -> Result<Vec<ImportRow>, CliError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| CliError::Local(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let rows = match format {
+        ImportFormat::Gpx => gpx::read_waypoints(&content),
+        ImportFormat::Csv => csv::read_rows(&content, mappings),
+    };
+
+    rows.map_err(|e| CliError::Usage(format!("{}: {}", path.display(), e)))
+}
+
+/// Checks values of cache
+fn validate(cache: NewCache) -> Result<NewCache, String> {
+    if !(-90.0..=90.0).contains(&cache.lat) {
+        return Err(format!("latitude {} is out of [-90, 90]", cache.lat));
+    }
+    if !(-180.0..=180.0).contains(&cache.long) {
+        return Err(format!("longitude {} is out of [-180, 180]", cache.long));
+    }
+    if cache.descrip.trim().is_empty() {
+        return Err("description is empty".to_string());
+    }
+    Ok(cache)
+}
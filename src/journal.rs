@@ -0,0 +1,220 @@
+//! Local append-only journal of mutating requests
+//!
+//! Every line of the journal file is one JSON [`Entry`]. Entries are never
+//! changed, an undo is recorded as a new entry referring to the undone one.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{cli::GlobalArgs, error::CliError, secrets};
+
+static JOURNAL_DIR_NAME: &str = "msd-cli";
+static JOURNAL_FILE_NAME: &str = "journal.jsonl";
+
+/// Kind of recorded change
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    UserCreate,
+    UserChange,
+    KeyGenerate,
+    KeyRevoke,
+    CacheCreate,
+    CacheChange,
+    CacheDelete,
+}
+
+impl Operation {
+    /// Command name, e.g. `cache change`
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::UserCreate => "user create",
+            Operation::UserChange => "user change",
+            Operation::KeyGenerate => "user keys generate",
+            Operation::KeyRevoke => "user keys revoke",
+            Operation::CacheCreate => "cache create",
+            Operation::CacheChange => "cache change",
+            Operation::CacheDelete => "cache delete",
+        }
+    }
+}
+
+/// Change to record, completed with id, time and server by [`Journal::append`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Record {
+    pub operation: Operation,
+    /// HTTP method and endpoint relative to API base, e.g. `PUT /cache/5`
+    pub method: String,
+    pub endpoint: String,
+    /// ID of changed user or cache
+    pub target: Option<i32>,
+    /// Number of changed key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nmb: Option<usize>,
+    /// Request body with secrets redacted
+    pub request: Value,
+    /// State of object fetched before the change
+    pub before: Option<Value>,
+    /// Object created by the change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Entry undone by this change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<usize>,
+}
+
+impl Record {
+    pub fn new(operation: Operation, method: &str, endpoint: String, target: Option<i32>) -> Self {
+        Self {
+            operation,
+            method: method.to_string(),
+            endpoint,
+            target,
+            nmb: None,
+            request: Value::Null,
+            before: None,
+            result: None,
+            undoes: None,
+        }
+    }
+
+    pub fn request(mut self, request: Value) -> Self {
+        self.request = secrets::redact(&request);
+        self
+    }
+
+    pub fn before(mut self, before: Value) -> Self {
+        self.before = Some(before);
+        self
+    }
+
+    pub fn result(mut self, result: Value) -> Self {
+        self.result = Some(secrets::redact(&result));
+        self
+    }
+
+    pub fn undoes(mut self, entry: usize) -> Self {
+        self.undoes = Some(entry);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    /// Number of entry, starting from 1
+    pub id: usize,
+    /// RFC 3339 time of the change
+    pub timestamp: String,
+    pub profile: Option<String>,
+    pub api_base: String,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// Journal at `--journal` or `$XDG_DATA_HOME/msd-cli/journal.jsonl`
+    pub fn open(global: &GlobalArgs) -> Result<Self, CliError> {
+        let path = match &global.journal {
+            Some(p) => p.clone(),
+            None => dirs::data_dir()
+                .map(|d| d.join(JOURNAL_DIR_NAME).join(JOURNAL_FILE_NAME))
+                .ok_or_else(|| {
+                    CliError::Local("Unable to locate data directory for journal".to_string())
+                })?,
+        };
+        Ok(Self { path })
+    }
+
+    fn error(&self, e: impl std::fmt::Display) -> CliError {
+        CliError::Local(format!("Journal {}: {}", self.path.display(), e))
+    }
+
+    /// All entries, oldest first. A missing journal is empty.
+    pub fn entries(&self) -> Result<Vec<Entry>, CliError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.error(e)),
+        };
+
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| self.error(format!("line {}: {}", i + 1, e)))
+            })
+            .collect()
+    }
+
+    /// Appends record made against server of `global`
+    pub fn append(&self, global: &GlobalArgs, record: Record) -> Result<Entry, CliError> {
+        let id = self.entries()?.last().map_or(1, |e| e.id + 1);
+        let entry = Entry {
+            id,
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            profile: global.profile.clone(),
+            api_base: global.get_api_base(),
+            record,
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| self.error(e))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.error(e))?;
+
+        let line = serde_json::to_string(&entry).map_err(|e| self.error(e))?;
+        writeln!(file, "{}", line).map_err(|e| self.error(e))?;
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::StructOpt;
+
+    use super::*;
+    use crate::cli::MainCliArgs;
+
+    #[test]
+    fn appends_numbered_entries() {
+        let path = std::env::temp_dir().join(format!("msd-journal-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let args = MainCliArgs::try_parse_from([
+            "msd-cli",
+            "--journal",
+            path.to_str().unwrap(),
+            "history",
+        ])
+        .unwrap();
+        let journal = Journal::open(&args.global).unwrap();
+        assert!(journal.entries().unwrap().is_empty());
+
+        let record = Record::new(Operation::UserChange, "PUT", "/user/1".to_string(), Some(1))
+            .request(json!({ "email": "a@b", "password": "secret" }));
+        journal.append(&args.global, record.clone()).unwrap();
+        journal.append(&args.global, record.undoes(1)).unwrap();
+
+        let entries = journal.entries().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].id, 2);
+        assert_eq!(entries[1].record.undoes, Some(1));
+        assert_eq!(entries[0].record.request["password"], "<redacted>");
+    }
+}
@@ -1,43 +1,68 @@
-use std::time::Duration;
+use std::process;
 
 use clap::StructOpt;
-use processors::ProcessorErrorStatus;
-use reqwest::{blocking::Client, header};
+use error::CliError;
+use processors::Context;
 
 extern crate serde;
 #[macro_use]
 extern crate serde_json;
 
+mod batch;
 mod cli;
+mod config;
+mod connection;
+mod credentials;
+mod curl;
+mod error;
+mod export;
+mod geo;
+mod import;
+mod journal;
+mod man;
+mod manifest;
+mod mock;
+mod output;
 mod processors;
-
-static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+mod secrets;
+mod shell;
+mod table;
+mod trace;
 
 fn main() {
-    let args = cli::MainCliArgs::parse();
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
 
-    let mut client_builder = Client::builder();
-    if let Some(k) = &args.api {
-        let mut headers = header::HeaderMap::new();
-        headers.insert("x-api-key", header::HeaderValue::from_str(k).unwrap());
+fn run() -> Result<(), CliError> {
+    let mut args = cli::MainCliArgs::parse();
 
-        client_builder = client_builder.default_headers(headers)
+    // Shell resolves profiles itself as they can be switched in session
+    if let cli::Command::Shell(shell_args) = &args.command {
+        return shell::run(shell_args, args.global);
     }
-
-    let mut client = client_builder
-        .user_agent(APP_USER_AGENT)
-        .connect_timeout(Duration::from_secs(5))
-        .build()
-        .expect("Failed to build client");
-
-    let processors = processors::init_processors();
-
-    for p in processors {
-        let res = p.process_args(&args, &mut client);
-        match res {
-            Ok(_) => break,
-            Err(ProcessorErrorStatus::NotMyCommand) => continue,
-            Err(ProcessorErrorStatus::Error) => break,
-        }
+    if let cli::Command::Batch(batch_args) = &args.command {
+        return batch::run(batch_args, args.global);
     }
+    // Mock server needs no client
+    if let cli::Command::MockServer(mock_args) = &args.command {
+        return mock::run(mock_args);
+    }
+
+    // Broken profile must not lock out config commands fixing it
+    let client = if args.command.is_local() {
+        connection::offline(&args.global)?
+    } else {
+        connection::connect(&mut args.global, args.command.uses_stored_key())?
+    };
+
+    let ctx = Context {
+        global: &args.global,
+        client: &client,
+        captured: None,
+    };
+
+    processors::process_command(&args.command, &ctx)
 }
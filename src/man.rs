@@ -0,0 +1,221 @@
+//! Man pages generated from clap definitions, one page per command
+//!
+//! Page of `msd-cli cache find` is `msd-cli-cache-find.1`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Arg, Command};
+use roff::{bold, italic, roman, Inline, Roff};
+
+use crate::{error::EXIT_CODES_HELP, secrets::API_KEY_ENV};
+
+/// Writes pages of `cmd` and all its subcommands to `dir`. Returns written files.
+pub fn write_pages(cmd: &Command, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let mut pages = Vec::new();
+    collect_pages(cmd, &mut vec![cmd.get_name().to_string()], &mut pages);
+
+    let mut files = Vec::new();
+    for (name, page) in pages {
+        let path = dir.join(format!("{}.1", name));
+        fs::write(&path, page.render()).map_err(|e| format!("{}: {}", path.display(), e))?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+fn collect_pages(cmd: &Command, names: &mut Vec<String>, pages: &mut Vec<(String, Roff)>) {
+    pages.push((names.join("-"), page(cmd, names)));
+
+    for sub in cmd.get_subcommands().filter(|s| !s.is_hide_set()) {
+        names.push(sub.get_name().to_string());
+        collect_pages(sub, names, pages);
+        names.pop();
+    }
+}
+
+fn page(cmd: &Command, names: &[String]) -> Roff {
+    let page_name = names.join("-");
+    let is_root = names.len() == 1;
+
+    let mut roff = Roff::new();
+    roff.control(
+        "TH",
+        [
+            page_name.to_uppercase().as_str(),
+            "1",
+            // Empty date, roff does not quote empty arguments
+            "\"\"",
+            &format!("{} {}", names[0], env!("CARGO_PKG_VERSION")),
+        ],
+    );
+
+    roff.control("SH", ["NAME"]);
+    let about = cmd.get_about().unwrap_or_default();
+    roff.text([roman(format!("{} - {}", page_name, about))]);
+
+    roff.control("SH", ["SYNOPSIS"]);
+    roff.text(synopsis(cmd, names));
+
+    if let Some(about) = cmd.get_long_about().or_else(|| cmd.get_about()) {
+        roff.control("SH", ["DESCRIPTION"]);
+        roff.text([roman(about)]);
+    }
+
+    let args = visible_args(cmd, is_root);
+
+    let positionals: Vec<_> = args.iter().filter(|a| a.is_positional()).collect();
+    if !positionals.is_empty() {
+        roff.control("SH", ["ARGUMENTS"]);
+        for arg in positionals {
+            roff.control("TP", []);
+            roff.text([bold(value_name(arg))]);
+            arg_help(&mut roff, arg);
+        }
+    }
+
+    let options: Vec<_> = args.iter().filter(|a| !a.is_positional()).collect();
+    if !options.is_empty() {
+        roff.control("SH", [if is_root { "GLOBAL OPTIONS" } else { "OPTIONS" }]);
+        for arg in options {
+            roff.control("TP", []);
+            roff.text(flags(arg));
+            arg_help(&mut roff, arg);
+        }
+    }
+
+    let subcommands: Vec<_> = cmd.get_subcommands().filter(|s| !s.is_hide_set()).collect();
+    if !subcommands.is_empty() {
+        roff.control("SH", ["COMMANDS"]);
+        for sub in subcommands {
+            roff.control("TP", []);
+            roff.text([bold(sub.get_name())]);
+            roff.text([
+                roman(format!("{} See ", sub.get_about().unwrap_or_default())),
+                bold(format!("{}-{}", page_name, sub.get_name())),
+                roman("(1)."),
+            ]);
+        }
+    }
+
+    if is_root {
+        roff.control("SH", ["ENVIRONMENT"]);
+        roff.control("TP", []);
+        roff.text([bold(API_KEY_ENV)]);
+        roff.text([roman(
+            "Api key used when --api and --api-file are not given.",
+        )]);
+
+        roff.control("SH", ["EXIT STATUS"]);
+        for line in EXIT_CODES_HELP.lines().skip(1) {
+            if let Some((code, meaning)) = line.trim().split_once(char::is_whitespace) {
+                roff.control("TP", []);
+                roff.text([bold(code)]);
+                roff.text([roman(meaning.trim())]);
+            }
+        }
+    } else {
+        roff.control("SH", ["SEE ALSO"]);
+        roff.text([
+            bold(names[0].as_str()),
+            roman("(1) for global options accepted by all commands."),
+        ]);
+    }
+
+    roff
+}
+
+fn synopsis(cmd: &Command, names: &[String]) -> Vec<Inline> {
+    let mut line = vec![bold(names.join(" "))];
+
+    for arg in visible_args(cmd, names.len() == 1) {
+        line.push(roman(" "));
+        let optional = !arg.is_required_set();
+        if optional {
+            line.push(roman("["));
+        }
+        if arg.is_positional() {
+            line.push(italic(value_name(arg)));
+        } else {
+            line.extend(flags(arg));
+        }
+        if optional {
+            line.push(roman("]"));
+        }
+    }
+
+    if cmd.get_subcommands().next().is_some() {
+        line.push(roman(" "));
+        line.push(italic("<COMMAND>"));
+    }
+    line
+}
+
+/// Arguments shown on page. Only root command has `--version`.
+fn visible_args<'a, 'help>(cmd: &'a Command<'help>, is_root: bool) -> Vec<&'a Arg<'help>> {
+    cmd.get_arguments()
+        .filter(|a| !a.is_hide_set())
+        .filter(|a| is_root || a.get_id() != "version")
+        .collect()
+}
+
+fn value_name(arg: &Arg) -> String {
+    match arg.get_value_names() {
+        Some(names) => names
+            .iter()
+            .map(|n| format!("<{}>", n))
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => format!("<{}>", arg.get_id().to_uppercase()),
+    }
+}
+
+/// Flag with value placeholder, e.g. `-i, --id <ID>`
+fn flags(arg: &Arg) -> Vec<Inline> {
+    let mut names = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(format!("-{}", short));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("--{}", long));
+    }
+
+    let mut inlines = vec![bold(names.join(", "))];
+    if arg.is_takes_value_set() {
+        inlines.push(roman(" "));
+        inlines.push(italic(value_name(arg)));
+    }
+    inlines
+}
+
+fn arg_help(roff: &mut Roff, arg: &Arg) {
+    let mut text = arg
+        .get_long_help()
+        .or_else(|| arg.get_help())
+        .unwrap_or_default()
+        .to_string();
+
+    if let Some(values) = arg.get_possible_values() {
+        let values: Vec<_> = values
+            .iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| v.get_name())
+            .collect();
+        text.push_str(&format!(" [possible values: {}]", values.join(", ")));
+    }
+
+    let defaults: Vec<_> = arg
+        .get_default_values()
+        .iter()
+        .map(|v| v.to_string_lossy())
+        .collect();
+    if !defaults.is_empty() && !arg.is_hide_default_value_set() {
+        text.push_str(&format!(" [default: {}]", defaults.join(", ")));
+    }
+
+    roff.text([roman(text)]);
+}
@@ -0,0 +1,301 @@
+//! Manifest of desired caches and plan of changes making server match it
+//!
+//! Manifest is a TOML file with a table per cache, keyed by a local name:
+//!
+//! ```toml
+//! [caches.red-square]
+//! lat = 55.7539
+//! long = 37.6208
+//! descrip = "Red square"
+//! hint = "Under the bench"
+//! ```
+//!
+//! Server ids of caches created from manifest are kept by name in a JSON state
+//! file, `caches.state.json` next to `caches.toml` by default.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use msd_client::{Cache, CacheChanges, NewCache};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::CliError;
+
+/// Fields of cache compared with manifest, in order of plan
+static FIELDS: &[&str] = &["lat", "long", "descrip", "hint"];
+
+/// Coordinates read back from server may differ in last digits
+const COORD_EPSILON: f64 = 1e-7;
+
+/// Desired caches by local name
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub caches: BTreeMap<String, NewCache>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| CliError::Local(format!("Failed to read {}: {}", path.display(), e)))?;
+        toml::from_str(&content).map_err(|e| CliError::Usage(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Server ids of caches managed by manifest
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct State {
+    /// Server the caches were created on
+    #[serde(default)]
+    pub api_base: Option<String>,
+    #[serde(default)]
+    pub caches: BTreeMap<String, i32>,
+}
+
+impl State {
+    /// Default state file of manifest, e.g. `caches.state.json` for `caches.toml`
+    pub fn default_path(manifest: &Path) -> PathBuf {
+        manifest.with_extension("state.json")
+    }
+
+    /// Loads state. A missing file is empty state.
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(state_error(path, e)),
+        };
+        serde_json::from_str(&content).map_err(|e| state_error(path, e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CliError> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| state_error(path, e))?;
+        fs::write(path, content + "\n").map_err(|e| state_error(path, e))
+    }
+}
+
+fn state_error(path: &Path, e: impl std::fmt::Display) -> CliError {
+    CliError::Local(format!("State {}: {}", path.display(), e))
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Create,
+    Change,
+    Delete,
+}
+
+/// Value of cache field before and after action. `None` if cache does not exist.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Change of one cache
+#[derive(Serialize, Debug)]
+pub struct Action {
+    #[serde(rename = "action")]
+    pub kind: ActionKind,
+    pub name: String,
+    /// Server id, `None` for cache to create
+    pub id: Option<i32>,
+    /// Changed fields, all fields for create and delete
+    pub fields: Vec<FieldChange>,
+    /// Cache from manifest
+    #[serde(skip)]
+    pub desired: Option<NewCache>,
+    /// Cache on server
+    #[serde(skip)]
+    pub current: Option<Cache>,
+}
+
+impl Action {
+    /// Changes sending only changed fields
+    pub fn changes(&self) -> CacheChanges {
+        let mut changes = CacheChanges::default();
+        if let Some(desired) = &self.desired {
+            for f in &self.fields {
+                match f.field {
+                    "lat" => changes.lat = Some(desired.lat),
+                    "long" => changes.long = Some(desired.long),
+                    "descrip" => changes.descrip = Some(desired.descrip.clone()),
+                    "hint" => changes.hint = Some(desired.hint.clone()),
+                    _ => {}
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Actions making server caches match manifest, in order of names
+#[derive(Debug)]
+pub struct Plan {
+    pub actions: Vec<Action>,
+}
+
+impl Plan {
+    /// Compares manifest with `current` caches on server by name
+    pub fn new(manifest: &Manifest, current: &BTreeMap<String, Cache>) -> Self {
+        let mut actions = Vec::new();
+
+        for (name, desired) in &manifest.caches {
+            let (kind, fields) = match current.get(name) {
+                None => (ActionKind::Create, fields(None, Some(desired))),
+                Some(cache) => {
+                    let fields = fields(Some(cache), Some(desired));
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    (ActionKind::Change, fields)
+                }
+            };
+            let current = current.get(name).cloned();
+            actions.push(Action {
+                kind,
+                name: name.clone(),
+                id: current.as_ref().map(|c| c.id),
+                fields,
+                desired: Some(desired.clone()),
+                current,
+            });
+        }
+
+        for (name, cache) in current {
+            if !manifest.caches.contains_key(name) {
+                actions.push(Action {
+                    kind: ActionKind::Delete,
+                    name: name.clone(),
+                    id: Some(cache.id),
+                    fields: fields(Some(cache), None),
+                    desired: None,
+                    current: Some(cache.clone()),
+                });
+            }
+        }
+        actions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self { actions }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn count(&self, kind: ActionKind) -> usize {
+        self.actions.iter().filter(|a| a.kind == kind).count()
+    }
+}
+
+/// Fields differing between cache on server and in manifest
+fn fields(current: Option<&Cache>, desired: Option<&NewCache>) -> Vec<FieldChange> {
+    let current = current.map(|c| json!(c));
+    let desired = desired.map(|c| json!(c));
+
+    FIELDS
+        .iter()
+        .filter_map(|&field| {
+            let before = current.as_ref().map(|c| c[field].clone());
+            let after = desired.as_ref().map(|c| c[field].clone());
+            let same = match (&before, &after) {
+                (Some(Value::Number(b)), Some(Value::Number(a))) => {
+                    let (b, a) = (
+                        b.as_f64().unwrap_or_default(),
+                        a.as_f64().unwrap_or_default(),
+                    );
+                    (b - a).abs() < COORD_EPSILON
+                }
+                (b, a) => b == a,
+            };
+            (!same).then_some(FieldChange {
+                field,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_creates_changes_and_deletes() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            [caches.oak]
+            lat = 1.0
+            long = 2.0
+            descrip = "Oak"
+            hint = "Roots"
+
+            [caches.elm]
+            lat = 3.0
+            long = 4.0
+            descrip = "Elm"
+            hint = "New hint"
+
+            [caches.pine]
+            lat = 5.0
+            long = 6.0
+            descrip = "Pine"
+            hint = "Cone"
+            "#,
+        )
+        .unwrap();
+
+        let cache = |id, lat, long, descrip: &str, hint: &str| Cache {
+            id,
+            owner: Some(1),
+            lat,
+            long,
+            descrip: descrip.to_string(),
+            hint: hint.to_string(),
+        };
+        let current = BTreeMap::from([
+            ("elm".to_string(), cache(1, 3.0, 4.0, "Elm", "Old hint")),
+            (
+                "pine".to_string(),
+                cache(2, 5.0, 6.00000001, "Pine", "Cone"),
+            ),
+            ("ash".to_string(), cache(3, 7.0, 8.0, "Ash", "Bark")),
+        ]);
+
+        let plan = Plan::new(&manifest, &current);
+        let summary: Vec<_> = plan
+            .actions
+            .iter()
+            .map(|a| (a.kind, a.name.as_str(), a.id, a.fields.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (ActionKind::Delete, "ash", Some(3), 4),
+                (ActionKind::Change, "elm", Some(1), 1),
+                (ActionKind::Create, "oak", None, 4),
+            ]
+        );
+
+        let change = &plan.actions[1];
+        assert_eq!(
+            change.fields,
+            [FieldChange {
+                field: "hint",
+                before: Some(json!("Old hint")),
+                after: Some(json!("New hint")),
+            }]
+        );
+        let changes = change.changes();
+        assert_eq!(changes.hint.as_deref(), Some("New hint"));
+        assert!(changes.lat.is_none());
+    }
+}
@@ -0,0 +1,524 @@
+//! Mock of MSD server for offline development and tests
+//!
+//! Serves the endpoints used by the client under `/api/v1`. Every request but
+//! user registration needs `x-api-key` of a user, users and their keys can be
+//! changed only with their own key, caches only with the key of their owner.
+//! Errors are answered with `{"error": true, "message": ...}`.
+//!
+//! State is kept in memory. With `--state` it is loaded from the JSON file on
+//! start and saved there after every change.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+};
+
+use msd_client::Cache;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{cli::MockServerArgs, error::CliError};
+
+static API_PREFIX: &str = "/api/v1";
+
+/// Runs server until process is killed
+pub fn run(args: &MockServerArgs) -> Result<(), CliError> {
+    let state = match &args.state {
+        Some(path) if path.exists() => {
+            let content = fs::read_to_string(path).map_err(|e| local(path, e))?;
+            serde_json::from_str(&content).map_err(|e| local(path, e))?
+        }
+        _ => State::default(),
+    };
+    let mut server = MockServer {
+        state,
+        path: args.state.clone(),
+    };
+
+    let listener = TcpListener::bind((args.bind.as_str(), args.port))
+        .map_err(|e| CliError::Local(format!("Failed to listen on {}: {}", args.bind, e)))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| CliError::Local(e.to_string()))?;
+
+    // First line is read by test harness to find port
+    println!("Mock server listening on http://{}{}", addr, API_PREFIX);
+    let _ = io::stdout().flush();
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = server.serve(stream) {
+                    eprintln!("Connection failed: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to accept connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+fn local(path: &std::path::Path, e: impl std::fmt::Display) -> CliError {
+    CliError::Local(format!("{}: {}", path.display(), e))
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct State {
+    users: BTreeMap<i32, MockUser>,
+    caches: BTreeMap<i32, Cache>,
+    /// Last issued cache id, ids of deleted caches are not reused
+    #[serde(default)]
+    cache_seq: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MockUser {
+    id: i32,
+    login: String,
+    email: String,
+    password: String,
+    /// Api keys by number
+    keys: BTreeMap<usize, String>,
+}
+
+#[derive(Deserialize)]
+struct NewUserBody {
+    login: String,
+    email: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct UserChangesBody {
+    email: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewCacheBody {
+    lat: f64,
+    long: f64,
+    descrip: String,
+    hint: String,
+}
+
+#[derive(Deserialize)]
+struct CacheChangesBody {
+    lat: Option<f64>,
+    long: Option<f64>,
+    descrip: Option<String>,
+    hint: Option<String>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    api_key: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Error answer: HTTP status and message
+type Failure = (u16, String);
+
+fn fail<T>(status: u16, message: &str) -> Result<T, Failure> {
+    Err((status, message.to_string()))
+}
+
+struct MockServer {
+    state: State,
+    path: Option<PathBuf>,
+}
+
+impl MockServer {
+    fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let request = match read_request(&mut reader)? {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+
+        let (status, body) = match self.handle(&request) {
+            Ok(mut doc) => {
+                if let Some(obj) = doc.as_object_mut() {
+                    obj.insert("error".to_string(), json!(false));
+                }
+                (200, doc)
+            }
+            Err((status, message)) => (status, json!({ "error": true, "message": message })),
+        };
+        eprintln!("{} {} {}", request.method, request.path, status);
+        write_response(stream, status, &body.to_string())
+    }
+
+    fn handle(&mut self, req: &Request) -> Result<Value, Failure> {
+        let path = match req.path.strip_prefix(API_PREFIX) {
+            Some(p) => p.trim_matches('/'),
+            None => return fail(404, "Not found"),
+        };
+        let segments: Vec<&str> = path.split('/').collect();
+
+        let method = req.method.as_str();
+        let doc = match (method, segments.as_slice()) {
+            ("POST", ["user"]) => self.create_user(req)?,
+            ("GET", ["user", id]) => self.get_user(req, parse_id(id)?)?,
+            ("PUT", ["user", id]) => self.update_user(req, parse_id(id)?)?,
+            ("POST", ["user", id, "keys"]) => self.generate_key(req, parse_id(id)?)?,
+            ("GET", ["user", id, "keys"]) => self.list_keys(req, parse_id(id)?)?,
+            ("GET", ["user", id, "keys", nmb]) => {
+                self.get_key(req, parse_id(id)?, parse_id(nmb)?)?
+            }
+            ("DELETE", ["user", id, "keys", nmb]) => {
+                self.revoke_key(req, parse_id(id)?, parse_id(nmb)?)?
+            }
+            ("POST", ["cache"]) => self.create_cache(req)?,
+            ("GET", ["cache"]) => self.find_caches(req)?,
+            ("GET", ["cache", id]) => self.get_cache(req, parse_id(id)?)?,
+            ("PUT", ["cache", id]) => self.update_cache(req, parse_id(id)?)?,
+            ("DELETE", ["cache", id]) => self.delete_cache(req, parse_id(id)?)?,
+            _ => return fail(404, "Not found"),
+        };
+
+        if method != "GET" {
+            self.save()?;
+        }
+        Ok(doc)
+    }
+
+    fn save(&self) -> Result<(), Failure> {
+        if let Some(path) = &self.path {
+            let content = serde_json::to_string_pretty(&self.state).unwrap_or_default();
+            fs::write(path, content).map_err(|e| (500, format!("Failed to save state: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// ID of user owning api key of request
+    fn authenticate(&self, req: &Request) -> Result<i32, Failure> {
+        let key = match &req.api_key {
+            Some(k) => k,
+            None => return fail(401, "Api key required"),
+        };
+        self.state
+            .users
+            .values()
+            .find(|u| u.keys.values().any(|k| k == key))
+            .map(|u| u.id)
+            .ok_or_else(|| (401, "Invalid api key".to_string()))
+    }
+
+    /// User of `id` who must be the one authenticated
+    fn own_user(&mut self, req: &Request, id: i32) -> Result<&mut MockUser, Failure> {
+        let caller = self.authenticate(req)?;
+        let user = match self.state.users.get_mut(&id) {
+            Some(u) => u,
+            None => return fail(404, "User not found"),
+        };
+        if caller != id {
+            return fail(403, "Forbidden");
+        }
+        Ok(user)
+    }
+
+    fn create_user(&mut self, req: &Request) -> Result<Value, Failure> {
+        let body: NewUserBody = parse_body(req)?;
+        if body.login.is_empty() || body.email.is_empty() || body.password.is_empty() {
+            return fail(400, "Login, email and password are required");
+        }
+        if self.state.users.values().any(|u| u.login == body.login) {
+            return fail(400, "User already exists");
+        }
+
+        let id = self.state.users.keys().last().map_or(1, |id| id + 1);
+        let key = new_key();
+        self.state.users.insert(
+            id,
+            MockUser {
+                id,
+                login: body.login,
+                email: body.email,
+                password: body.password,
+                keys: BTreeMap::from([(1, key.clone())]),
+            },
+        );
+        Ok(json!({ "id": id, "api_key": key }))
+    }
+
+    fn get_user(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        self.authenticate(req)?;
+        match self.state.users.get(&id) {
+            Some(u) => Ok(json!({ "id": u.id, "login": u.login, "email": u.email })),
+            None => fail(404, "User not found"),
+        }
+    }
+
+    fn update_user(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        let body: UserChangesBody = parse_body(req)?;
+        let user = self.own_user(req, id)?;
+        if let Some(email) = body.email {
+            user.email = email;
+        }
+        if let Some(password) = body.password {
+            user.password = password;
+        }
+        Ok(json!({}))
+    }
+
+    fn generate_key(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        let user = self.own_user(req, id)?;
+        let nmb = user.keys.keys().last().map_or(1, |n| n + 1);
+        let key = new_key();
+        user.keys.insert(nmb, key.clone());
+        Ok(json!({ "key": key }))
+    }
+
+    fn list_keys(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        let user = self.own_user(req, id)?;
+        let keys: Vec<_> = user
+            .keys
+            .iter()
+            .map(|(nmb, key)| json!({ "nmb": nmb, "api_key": key }))
+            .collect();
+        Ok(json!({ "keys": keys }))
+    }
+
+    fn get_key(&mut self, req: &Request, id: i32, nmb: i32) -> Result<Value, Failure> {
+        let user = self.own_user(req, id)?;
+        match user.keys.get(&(nmb as usize)) {
+            Some(key) => Ok(json!({ "key": key })),
+            None => fail(404, "Key not found"),
+        }
+    }
+
+    fn revoke_key(&mut self, req: &Request, id: i32, nmb: i32) -> Result<Value, Failure> {
+        let user = self.own_user(req, id)?;
+        match user.keys.remove(&(nmb as usize)) {
+            Some(_) => Ok(json!({})),
+            None => fail(404, "Key not found"),
+        }
+    }
+
+    fn create_cache(&mut self, req: &Request) -> Result<Value, Failure> {
+        let owner = self.authenticate(req)?;
+        let body: NewCacheBody = parse_body(req)?;
+        check_coordinates(Some(body.lat), Some(body.long))?;
+        if body.descrip.is_empty() {
+            return fail(400, "Description is required");
+        }
+
+        self.state.cache_seq += 1;
+        let id = self.state.cache_seq;
+        let cache = Cache {
+            id,
+            owner: Some(owner),
+            lat: body.lat,
+            long: body.long,
+            descrip: body.descrip,
+            hint: body.hint,
+        };
+        self.state.caches.insert(id, cache.clone());
+        Ok(json!(cache))
+    }
+
+    fn find_caches(&mut self, req: &Request) -> Result<Value, Failure> {
+        self.authenticate(req)?;
+
+        let mut user_id = None;
+        let (mut min_lat, mut max_lat) = (f64::MIN, f64::MAX);
+        let (mut min_long, mut max_long) = (f64::MIN, f64::MAX);
+        let (mut limit, mut offset) = (usize::MAX, 0);
+        for (name, value) in &req.query {
+            let number = || {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| (400, format!("Invalid {}: {}", name, value)))
+            };
+            match name.as_str() {
+                "user_id" => user_id = Some(number()? as i32),
+                "min_lat" => min_lat = number()?,
+                "max_lat" => max_lat = number()?,
+                "min_long" => min_long = number()?,
+                "max_long" => max_long = number()?,
+                "limit" => limit = number()? as usize,
+                "offset" => offset = number()? as usize,
+                _ => return fail(400, &format!("Unknown parameter {}", name)),
+            }
+        }
+
+        let caches: Vec<_> = self
+            .state
+            .caches
+            .values()
+            .filter(|c| user_id.is_none() || c.owner == user_id)
+            .filter(|c| (min_lat..=max_lat).contains(&c.lat))
+            .filter(|c| (min_long..=max_long).contains(&c.long))
+            .skip(offset)
+            .take(limit)
+            .collect();
+        Ok(json!({ "caches": caches }))
+    }
+
+    fn get_cache(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        self.authenticate(req)?;
+        match self.state.caches.get(&id) {
+            Some(c) => Ok(json!({ "caches": c })),
+            None => fail(404, "Cache not found"),
+        }
+    }
+
+    /// Cache of `id` which must be owned by authenticated user
+    fn own_cache(&mut self, req: &Request, id: i32) -> Result<&mut Cache, Failure> {
+        let caller = self.authenticate(req)?;
+        let cache = match self.state.caches.get_mut(&id) {
+            Some(c) => c,
+            None => return fail(404, "Cache not found"),
+        };
+        if cache.owner != Some(caller) {
+            return fail(403, "Forbidden");
+        }
+        Ok(cache)
+    }
+
+    fn update_cache(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        let body: CacheChangesBody = parse_body(req)?;
+        check_coordinates(body.lat, body.long)?;
+        let cache = self.own_cache(req, id)?;
+
+        if let Some(lat) = body.lat {
+            cache.lat = lat;
+        }
+        if let Some(long) = body.long {
+            cache.long = long;
+        }
+        if let Some(descrip) = body.descrip {
+            cache.descrip = descrip;
+        }
+        if let Some(hint) = body.hint {
+            cache.hint = hint;
+        }
+        Ok(json!({}))
+    }
+
+    fn delete_cache(&mut self, req: &Request, id: i32) -> Result<Value, Failure> {
+        self.own_cache(req, id)?;
+        self.state.caches.remove(&id);
+        Ok(json!({}))
+    }
+}
+
+fn new_key() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+fn parse_id(s: &str) -> Result<i32, Failure> {
+    s.parse().map_err(|_| (404, "Not found".to_string()))
+}
+
+fn parse_body<T: DeserializeOwned>(req: &Request) -> Result<T, Failure> {
+    serde_json::from_slice(&req.body).map_err(|e| (400, format!("Invalid body: {}", e)))
+}
+
+fn check_coordinates(lat: Option<f64>, long: Option<f64>) -> Result<(), Failure> {
+    if lat.is_some_and(|l| !(-90.0..=90.0).contains(&l)) {
+        return fail(400, "Latitude must be within -90..90");
+    }
+    if long.is_some_and(|l| !(-180.0..=180.0).contains(&l)) {
+        return fail(400, "Longitude must be within -180..180");
+    }
+    Ok(())
+}
+
+/// Reads request head and body. Returns `None` when connection is closed before request.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut api_key = None;
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "x-api-key" => api_key = Some(value.to_string()),
+                "content-length" => length = value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let query = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (decode(k), decode(v))
+        })
+        .collect();
+
+    Ok(Some(Request {
+        method,
+        path: path.to_string(),
+        query,
+        api_key,
+        body,
+    }))
+}
+
+/// Decodes `+` and `%XX` of query component
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'+', _) => out.push(b' '),
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 2;
+            }
+            (b, _) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
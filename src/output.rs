@@ -0,0 +1,140 @@
+//! Structured output of commands
+//!
+//! With `--output json|ndjson|yaml` every command prints exactly one document
+//! on stdout and human readable messages are suppressed. Errors are printed to
+//! stderr in all modes.
+//!
+//! Documents by command:
+//! * `user create` - `{"id": <id>, "api_key": <key>}`, `id` is optional
+//! * `user view` - `{"id": <id>, "login": <login>, "email": <email>}`
+//! * `user change`, `cache change|delete` - `{"id": <id>}`
+//! * `user keys generate` - `{"key": <key>}`
+//! * `user keys view` - array of `{"nmb": <number>, "api_key": <key>}`
+//! * `user keys revoke` - `{"id": <user id>, "nmb": <number>}`
+//! * `cache create|view` - cache object
+//!   `{"id", "owner", "lat", "long", "descrip", "hint"}`
+//! * `cache find` - array of cache objects. With `--near` every object also has
+//!   `distance` in meters and `bearing` in degrees, nearest first. With `--all`
+//!   the array is printed item by item while pages arrive
+//! * `cache plan` - array of actions `{"action": "create|change|delete",
+//!   "name", "id", "fields"}`, every field is `{"field", "before", "after"}`
+//! * `cache apply` - `{"applied": [{"action", "name", "id"}], "failed":
+//!   [{"action", "name", "error"}]}`
+//! * `config list` - array of `{"name": <name>, "default": <bool>}`
+//! * `config show` - `{"name": <name|null>, "profile": <profile|null>, "api_base": <url>}`
+//! * `config add|remove` - `{"name": <name>}`
+//! * `history` - array of journal entries `{"id", "timestamp", "profile",
+//!   "api_base", "operation", "method", "endpoint", "target", "request",
+//!   "before"}`, optionally with `nmb`, `result` and `undoes`
+//! * `undo` - `{"undone": <entry>, "entry": <new entry|null>, "id": <id>}`,
+//!   `id` is the ID of the re-created cache or the changed object
+//! * `login` - `{"id", "api_base", "store": "keyring|file"}`
+//! * `logout` - `{"api_base": <url>, "logged_out": <bool>}`
+//! * `whoami` - `{"id", "login", "email", "api_base", "store", "active"}`,
+//!   `active` is false when another api key is given by options or profile
+//! * `batch` - `{"commands": [{"line", "command", "result", "error"}],
+//!   "succeeded", "failed"}`, `result` is the document of the command
+//!
+//! With `--dry-run` a command that would change data prints
+//! `{"dry_run": true, "method", "url", "headers", "body"}` of its request
+//! instead, api key and passwords are redacted. `cache apply` prints an array
+//! of such objects, one per action, and `cache import` prints its preview of
+//! rows. `batch` prints the request of every line, but a line using `$LAST_ID`
+//! of an object created in dry run fails, as the object has no ID.
+//!
+//! `ndjson` prints every array element on its own line, objects take one line.
+
+use clap::ArgEnum;
+use serde_json::Value;
+
+/// Output format of command results
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// Pretty printed JSON document
+    Json,
+    /// One compact JSON value per line
+    Ndjson,
+    /// YAML document
+    Yaml,
+}
+
+impl OutputFormat {
+    pub fn is_text(self) -> bool {
+        self == OutputFormat::Text
+    }
+}
+
+/// Prints document in requested format. Does nothing in text mode.
+pub fn print_document(format: OutputFormat, doc: &Value) {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(doc).unwrap_or_default())
+        }
+        OutputFormat::Ndjson => match doc {
+            Value::Array(items) => {
+                for item in items {
+                    println!("{}", item);
+                }
+            }
+            _ => println!("{}", doc),
+        },
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(doc).unwrap_or_default()),
+    }
+}
+
+/// Array document printed item by item. Does nothing in text mode.
+pub struct ArrayStream {
+    format: OutputFormat,
+    count: usize,
+}
+
+impl ArrayStream {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format, count: 0 }
+    }
+
+    pub fn push(&mut self, item: &Value) {
+        let first = self.count == 0;
+        self.count += 1;
+
+        match self.format {
+            OutputFormat::Text => {}
+            OutputFormat::Json => {
+                print!("{}", if first { "[\n" } else { ",\n" });
+                let pretty = serde_json::to_string_pretty(item).unwrap_or_default();
+                print!("{}", indent(&pretty, "  ", "  "));
+            }
+            OutputFormat::Ndjson => println!("{}", item),
+            OutputFormat::Yaml => {
+                if first {
+                    println!("---");
+                }
+                let yaml = serde_yaml::to_string(item).unwrap_or_default();
+                let yaml = yaml.strip_prefix("---\n").unwrap_or(&yaml);
+                println!("{}", indent(yaml.trim_end(), "- ", "  "));
+            }
+        }
+    }
+
+    /// Closes array, must be called after the last item
+    pub fn finish(self) {
+        match (self.format, self.count) {
+            (OutputFormat::Json, 0) => println!("[]"),
+            (OutputFormat::Json, _) => println!("\n]"),
+            (OutputFormat::Yaml, 0) => println!("---\n[]"),
+            _ => {}
+        }
+    }
+}
+
+/// Prefixes first line with `first` and other lines with `rest`
+fn indent(text: &str, first: &str, rest: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| format!("{}{}", if i == 0 { first } else { rest }, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
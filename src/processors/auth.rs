@@ -0,0 +1,109 @@
+use msd_client::ClientError;
+
+use crate::{
+    cli::*,
+    connection,
+    credentials::{Credentials, Store, StoreKind},
+    error::CliError,
+    processors::print_result,
+    secrets,
+};
+
+use super::{Context, Processor};
+
+fn describe(store: &Store, kind: StoreKind) -> String {
+    match kind {
+        StoreKind::Keyring => "system keyring".to_string(),
+        StoreKind::File => store.file().display().to_string(),
+    }
+}
+
+impl Processor for LoginArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let api_key = if self.key_stdin {
+            secrets::read_stdin()?
+        } else {
+            secrets::prompt_api_key("--key-stdin")?
+        };
+
+        let mut global = ctx.global.clone();
+        global.api = Some(api_key.clone());
+        let client = connection::connect(&mut global, false)?;
+        // Keys are listed only to their owner. User itself is shown by whoami.
+        client.list_keys(self.id).map_err(|e| match &e {
+            ClientError::HttpStatus(status) | ClientError::Server { status, .. }
+                if matches!(status.as_u16(), 401 | 403) =>
+            {
+                CliError::Usage(format!(
+                    "Api key is not accepted as key of user {}: {}",
+                    self.id, e
+                ))
+            }
+            _ => CliError::Client(e),
+        })?;
+
+        let api_base = ctx.global.get_api_base();
+        let store = Store::open(ctx.global)?;
+        let kind = store.save(
+            &api_base,
+            &Credentials {
+                user_id: self.id,
+                api_key,
+            },
+        )?;
+
+        let doc = json!({
+            "id": self.id,
+            "api_base": api_base,
+            "store": kind,
+        });
+        print_result(ctx, &doc, |_| {
+            println!("Logged in to {} as user {}", api_base, self.id);
+            println!("Api key is stored in {}", describe(&store, kind));
+        });
+        Ok(())
+    }
+}
+
+pub fn logout(ctx: &Context) -> Result<(), CliError> {
+    let api_base = ctx.global.get_api_base();
+    let removed = Store::open(ctx.global)?.remove(&api_base)?;
+
+    let doc = json!({ "api_base": api_base, "logged_out": removed });
+    print_result(ctx, &doc, |_| {
+        if removed {
+            println!("Logged out of {}", api_base);
+        } else {
+            println!("Not logged in to {}", api_base);
+        }
+    });
+    Ok(())
+}
+
+pub fn whoami(ctx: &Context) -> Result<(), CliError> {
+    let api_base = ctx.global.get_api_base();
+    let store = Store::open(ctx.global)?;
+    let (credentials, kind) = store
+        .load(&api_base)?
+        .ok_or_else(|| CliError::Usage(format!("Not logged in to {}, use login", api_base)))?;
+    let user = ctx.client.get_user(credentials.user_id)?;
+    // Key of --api, environment or profile takes precedence over login
+    let active = ctx.global.api.as_deref() == Some(credentials.api_key.as_str());
+
+    let doc = json!({
+        "id": user.id,
+        "login": user.login,
+        "email": user.email,
+        "api_base": api_base,
+        "store": kind,
+        "active": active,
+    });
+    print_result(ctx, &doc, |_| {
+        println!("{} (user {}, {})", user.login, user.id, user.email);
+        println!("Api key is stored in {}", describe(&store, kind));
+        if !active {
+            println!("Requests use another api key given by options or profile");
+        }
+    });
+    Ok(())
+}
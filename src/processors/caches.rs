@@ -1,168 +1,338 @@
-use reqwest::blocking::Client;
+use std::collections::BTreeMap;
+
+use msd_client::{Cache, CacheChanges, CacheFilter, NewCache};
+use serde::Serialize;
+use serde_json::Value;
 
 use crate::{
     cli::*,
-    processors::{print_json_value, print_json_value_wo_error},
+    error::CliError,
+    export::export_caches,
+    geo::{self, Point},
+    journal::{Operation, Record},
+    output::ArrayStream,
+    processors::{fetch_before, print_json_value, print_result, record, sent},
+    table::{default_cell, TableSpec},
 };
 
-use super::{basic_server_response_check, Processor, ProcessorErrorStatus};
-
-pub struct CacheCreateProcessor;
-impl Processor for CacheCreateProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::Cache(cache_args) = &args.command {
-            if let CacheCommand::Create(cmd_args) = &cache_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/cache/", api_path);
-
-                let res = client
-                    .post(req_url)
-                    .json(&json!(
-                        {
-                            "lat": cmd_args.lat,
-                            "long": cmd_args.long,
-
-                            "descrip": cmd_args.descrip,
-                            "hint": cmd_args.hint,
-                        }
-                    ))
-                    .send();
-
-                let json = basic_server_response_check(res, args)?;
-
-                println!("Cache created:");
-                print_json_value_wo_error(&json);
+use super::{Context, Processor};
 
-                return Ok(());
-            }
+impl Processor for CacheCreateArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let new_cache = NewCache {
+            lat: self.lat,
+            long: self.long,
+
+            descrip: self.descrip.clone(),
+            hint: self.hint.clone(),
+        };
+        let Some(cache) = sent(ctx, ctx.client.create_cache(&new_cache)?) else {
+            return Ok(());
+        };
+        record(ctx, created_record(&new_cache, &cache));
+
+        print_result(ctx, &cache, |cache| {
+            println!("Cache created:");
+            print_json_value(&json!(cache));
+        });
+
+        Ok(())
+    }
+}
+
+/// Journal record of cache creation
+pub fn created_record(new_cache: &NewCache, cache: &Cache) -> Record {
+    Record::new(
+        Operation::CacheCreate,
+        "POST",
+        "/cache/".to_string(),
+        Some(cache.id),
+    )
+    .request(json!(new_cache))
+    .result(json!(cache))
+}
+
+/// Journal record of cache change, `before` is cache as it was
+pub fn changed_record(id: i32, changes: &CacheChanges, before: Value) -> Record {
+    Record::new(
+        Operation::CacheChange,
+        "PUT",
+        format!("/cache/{}", id),
+        Some(id),
+    )
+    .request(json!(changes))
+    .before(before)
+}
+
+/// Journal record of cache deletion, `before` is deleted cache
+pub fn deleted_record(id: i32, before: Value) -> Record {
+    Record::new(
+        Operation::CacheDelete,
+        "DELETE",
+        format!("/cache/{}", id),
+        Some(id),
+    )
+    .before(before)
+}
+
+/// Cache found by radius search
+#[derive(Serialize)]
+struct NearCache {
+    #[serde(flatten)]
+    cache: Cache,
+    /// Distance from search center in meters
+    distance: f64,
+    /// Bearing from search center in degrees
+    bearing: f64,
+}
+
+/// Finds caches within `radius` meters around `center`, nearest first
+fn find_near(
+    ctx: &Context,
+    args: &CacheFindArgs,
+    center: Point,
+    radius: f64,
+) -> Result<Vec<NearCache>, CliError> {
+    // Boxes split on antimeridian may return same caches twice
+    let mut found = BTreeMap::new();
+    for bbox in geo::bounding_boxes(center, radius) {
+        let filter = CacheFilter {
+            user_id: args.user,
+            min_lat: Some(bbox.min_lat),
+            max_lat: Some(bbox.max_lat),
+            min_long: Some(bbox.min_long),
+            max_long: Some(bbox.max_long),
+            ..Default::default()
+        };
+        for cache in ctx.client.find_caches(&filter)? {
+            found.insert(cache.id, cache);
         }
+    }
+
+    let mut near: Vec<_> = found
+        .into_values()
+        .filter_map(|cache| {
+            let point = Point {
+                lat: cache.lat,
+                long: cache.long,
+            };
+            let distance = geo::distance(center, point);
+            (distance <= radius).then(|| NearCache {
+                distance,
+                bearing: geo::bearing(center, point),
+                cache,
+            })
+        })
+        .collect();
+    near.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    Ok(near)
+}
+
+static CACHE_TABLE: TableSpec = TableSpec {
+    columns: &["id", "owner", "lat", "long", "descrip", "hint"],
+    truncated: Some("descrip"),
+};
+
+static NEAR_CACHE_TABLE: TableSpec = TableSpec {
+    columns: &[
+        "id", "distance", "bearing", "owner", "lat", "long", "descrip", "hint",
+    ],
+    truncated: Some("descrip"),
+};
+
+fn near_cell(column: &str, value: &Value) -> String {
+    match (column, value.as_f64()) {
+        ("distance", Some(d)) => format_distance(d),
+        ("bearing", Some(b)) => format!("{:.0}° {}", b, geo::compass_point(b)),
+        _ => default_cell(column, value),
+    }
+}
 
-        Err(ProcessorErrorStatus::NotMyCommand)
+/// Exports caches if requested. Returns `true` if result must not be printed.
+fn export(args: &ExportArgs, caches: &[Cache]) -> Result<bool, CliError> {
+    if let Some(format) = args.export {
+        export_caches(format, args.export_file.as_deref(), caches)?;
     }
+    Ok(args.to_stdout())
 }
 
-pub struct CacheFindProcessor;
-impl Processor for CacheFindProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::Cache(cache_args) = &args.command {
-            if let CacheCommand::Find(cmd_args) = &cache_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/cache/", api_path);
-
-                let res = client
-                    .get(req_url)
-                    .query(&CacheFindArgsServer::new(cmd_args))
-                    .send();
-
-                let json = basic_server_response_check(res, args)?;
-
-                let caches_array = json
-                    .get("caches")
-                    .expect("Server error: Field caches not found")
-                    .as_array()
-                    .expect("Server error: Field caches is not array");
+fn format_distance(meters: f64) -> String {
+    if meters < 1000.0 {
+        format!("{:.0} m", meters)
+    } else {
+        format!("{:.2} km", meters / 1000.0)
+    }
+}
+
+impl Processor for CacheFindArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        if let (Some(center), Some(radius)) = (self.near, self.radius) {
+            let mut caches = find_near(ctx, self, center, radius)?;
+            self.table.sort(&NEAR_CACHE_TABLE, &mut caches)?;
+
+            let plain: Vec<_> = caches.iter().map(|c| c.cache.clone()).collect();
+            if export(&self.export, &plain)? {
+                return Ok(());
+            }
+
+            print_result(ctx, &caches, |caches| {
+                if self.table.is_table() && !caches.is_empty() {
+                    return self.table.print(&NEAR_CACHE_TABLE, caches, near_cell);
+                }
 
                 println!("Cache find result:");
-                if caches_array.is_empty() {
+                if caches.is_empty() {
                     println!("\tNo caches");
-                } else {
-                    for c in caches_array {
-                        println!("Cache {}", c.get("id").unwrap().as_u64().unwrap());
-                        print_json_value(&c);
-                    }
                 }
+                for c in caches {
+                    println!(
+                        "Cache {} ({}, {:.0}° {})",
+                        c.cache.id,
+                        format_distance(c.distance),
+                        c.bearing,
+                        geo::compass_point(c.bearing)
+                    );
+                    print_json_value(&json!(c.cache));
+                }
+            });
 
-                return Ok(());
+            return Ok(());
+        }
+
+        let filter = CacheFilter::from(self);
+        let mut caches = if self.all {
+            // Batch prints whole result in its summary
+            if self.export.export.is_none() && ctx.captured.is_none() {
+                return self.stream_all(ctx, &filter);
             }
+            // Export formats are written at once
+            ctx.client
+                .find_caches_paged(&filter, self.page_size)
+                .collect::<Result<Vec<_>, _>>()?
+                .concat()
+        } else {
+            ctx.client.find_caches(&filter)?
+        };
+        self.table.sort(&CACHE_TABLE, &mut caches)?;
+
+        if export(&self.export, &caches)? {
+            return Ok(());
         }
 
-        Err(ProcessorErrorStatus::NotMyCommand)
+        print_result(ctx, &caches, |caches| {
+            if self.table.is_table() && !caches.is_empty() {
+                return self.table.print(&CACHE_TABLE, caches, default_cell);
+            }
+
+            println!("Cache find result:");
+            if caches.is_empty() {
+                println!("\tNo caches");
+            } else {
+                for c in caches {
+                    println!("Cache {}", c.id);
+                    print_json_value(&json!(c));
+                }
+            }
+        });
+
+        Ok(())
     }
 }
 
-pub struct CacheViewProcessor;
-impl Processor for CacheViewProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::Cache(cache_args) = &args.command {
-            if let CacheCommand::View(cmd_args) = &cache_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/cache/{}", api_path, cmd_args.id);
+impl CacheFindArgs {
+    /// Prints caches page by page, so memory use does not depend on result size
+    fn stream_all(&self, ctx: &Context, filter: &CacheFilter) -> Result<(), CliError> {
+        let text = ctx.global.output.is_text();
+        let mut stream = ArrayStream::new(ctx.global.output);
+        let mut table =
+            (text && self.table.is_table()).then(|| self.table.stream(&CACHE_TABLE, default_cell));
 
-                let res = client.get(req_url).send();
-
-                let json = basic_server_response_check(res, args)?;
+        if text && table.is_none() {
+            println!("Cache find result:");
+        }
 
-                let cache = json
-                    .get("caches")
-                    .expect("Server error: Field caches not found");
+        let mut count = 0;
+        for page in ctx.client.find_caches_paged(filter, self.page_size) {
+            let page = match page {
+                Ok(page) => page,
+                Err(e) => {
+                    stream.finish();
+                    return Err(e.into());
+                }
+            };
+            count += page.len();
 
-                println!("Cache view:");
-                print_json_value(cache);
+            if !text {
+                page.iter().for_each(|c| stream.push(&json!(c)));
+            } else if let Some(table) = &mut table {
+                table.push(&page);
+            } else {
+                for c in &page {
+                    println!("Cache {}", c.id);
+                    print_json_value(&json!(c));
+                }
+            }
+        }
+        stream.finish();
 
-                return Ok(());
+        if text && count == 0 {
+            if table.is_some() {
+                println!("Cache find result:");
             }
+            println!("\tNo caches");
+        }
+        Ok(())
+    }
+}
+
+impl Processor for CacheViewArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let cache = ctx.client.get_cache(self.id)?;
+
+        if export(&self.export, std::slice::from_ref(&cache))? {
+            return Ok(());
         }
 
-        Err(ProcessorErrorStatus::NotMyCommand)
+        print_result(ctx, &cache, |cache| {
+            println!("Cache view:");
+            print_json_value(&json!(cache));
+        });
+
+        Ok(())
     }
 }
 
-pub struct CacheChangeProcessor;
-impl Processor for CacheChangeProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::Cache(cache_args) = &args.command {
-            if let CacheCommand::Change(cmd_args) = &cache_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/cache/{}", api_path, cmd_args.id);
-
-                let res = client.put(req_url).json(&cmd_args).send();
-
-                let _ = basic_server_response_check(res, args)?;
-                println!("Cache edited");
-                return Ok(());
-            }
+impl Processor for CacheChangeArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let changes = CacheChanges {
+            lat: self.lat,
+            long: self.long,
+            descrip: self.descrip.clone(),
+            hint: self.hint.clone(),
+        };
+        let before = fetch_before(ctx, |c| c.get_cache(self.id))?;
+        if sent(ctx, ctx.client.update_cache(self.id, &changes)?).is_none() {
+            return Ok(());
         }
+        record(ctx, changed_record(self.id, &changes, before));
 
-        Err(ProcessorErrorStatus::NotMyCommand)
+        print_result(ctx, &json!({ "id": self.id }), |_| println!("Cache edited"));
+        Ok(())
     }
 }
 
-pub struct CacheDeleteProcessor;
-impl Processor for CacheDeleteProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::Cache(cache_args) = &args.command {
-            if let CacheCommand::Delete(cmd_args) = &cache_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/cache/{}", api_path, cmd_args.id);
-
-                let res = client.delete(req_url).send();
-
-                let _ = basic_server_response_check(res, args)?;
-                println!("Cache deleted");
-                return Ok(());
-            }
+impl Processor for CacheDeleteArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let before = fetch_before(ctx, |c| c.get_cache(self.id))?;
+        if sent(ctx, ctx.client.delete_cache(self.id)?).is_none() {
+            return Ok(());
         }
+        record(ctx, deleted_record(self.id, before));
 
-        Err(ProcessorErrorStatus::NotMyCommand)
+        print_result(ctx, &json!({ "id": self.id }), |_| {
+            println!("Cache deleted")
+        });
+        Ok(())
     }
 }
@@ -0,0 +1,89 @@
+use std::{collections::BTreeSet, io};
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+use msd_client::CacheFilter;
+
+use crate::{cli::*, error::CliError, man};
+
+use super::{print_result, Context, Processor};
+
+/// Completes `--id` of cache commands with cache IDs and other IDs with user IDs
+static BASH_DYNAMIC: &str = r#"
+_{bin}_ids() {
+    local cur prev kind
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "${prev}" in
+        --user)
+            kind="users"
+            ;;
+        --id|-i)
+            case " ${COMP_WORDS[*]} " in
+                *" cache "*) kind="caches" ;;
+                *) kind="users" ;;
+            esac
+            ;;
+    esac
+    if [[ -n "${kind}" ]] ; then
+        COMPREPLY=( $(compgen -W "$({bin} complete-ids ${kind} 2>/dev/null)" -- "${cur}") )
+        return 0
+    fi
+    _{bin} "$@"
+}
+
+complete -F _{bin}_ids -o bashdefault -o default {bin}
+"#;
+
+static FISH_DYNAMIC: &str = r#"
+complete -c {bin} -n "__fish_seen_subcommand_from cache" -s i -l id -xa "({bin} complete-ids caches 2>/dev/null)"
+complete -c {bin} -n "__fish_seen_subcommand_from user" -s i -l id -xa "({bin} complete-ids users 2>/dev/null)"
+complete -c {bin} -n "__fish_seen_subcommand_from find" -l user -xa "({bin} complete-ids users 2>/dev/null)"
+"#;
+
+impl Processor for CompletionsArgs {
+    fn process(&self, _ctx: &Context) -> Result<(), CliError> {
+        let mut cmd = MainCliArgs::command();
+        let bin = cmd.get_name().to_string();
+
+        clap_complete::generate(self.shell, &mut cmd, &bin, &mut io::stdout());
+
+        // IDs are asked from server while completing
+        let dynamic = match self.shell {
+            Shell::Bash => BASH_DYNAMIC,
+            Shell::Fish => FISH_DYNAMIC,
+            _ => "",
+        };
+        print!("{}", dynamic.replace("{bin}", &bin));
+        Ok(())
+    }
+}
+
+impl Processor for ManArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let files =
+            man::write_pages(&MainCliArgs::command(), &self.dir).map_err(CliError::Local)?;
+
+        print_result(ctx, &files, |files| {
+            for f in files {
+                println!("Written {}", f.display());
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Processor for CompleteIdsArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let caches = ctx.client.find_caches(&CacheFilter::default())?;
+
+        let ids: BTreeSet<i32> = match self.kind {
+            IdKind::Caches => caches.iter().map(|c| c.id).collect(),
+            IdKind::Users => caches.iter().filter_map(|c| c.owner).collect(),
+        };
+        for id in ids {
+            println!("{}", id);
+        }
+        Ok(())
+    }
+}
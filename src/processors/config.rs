@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cli::*,
+    config::{Config, Profile},
+    error::CliError,
+};
+
+use super::{print_result, Context, Processor};
+
+fn load_config(ctx: &Context) -> Result<(PathBuf, Config), CliError> {
+    let path = Config::path(ctx.global.config.as_deref())?;
+    let config = Config::load(&path)?;
+    Ok((path, config))
+}
+
+fn save_config(path: &Path, config: &Config) -> Result<(), CliError> {
+    Ok(config.save(path)?)
+}
+
+/// Shortest secret with its end shown by mask
+static MIN_PARTLY_SHOWN: usize = 16;
+
+fn mask_secret(secret: &str) -> String {
+    if secret.chars().count() < MIN_PARTLY_SHOWN {
+        return "****".to_string();
+    }
+    let visible: String = secret
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("****{}", visible)
+}
+
+fn print_profile(profile: &Profile) {
+    if let Some(url) = &profile.base_url {
+        println!("\tbase_url: {}", url);
+    }
+    if let Some(key) = &profile.api_key {
+        println!("\tapi_key: {}", mask_secret(key));
+    }
+    if let Some(t) = profile.connect_timeout {
+        println!("\tconnect_timeout: {}", t);
+    }
+    if let Some(t) = profile.timeout {
+        println!("\ttimeout: {}", t);
+    }
+    if let Some(path) = &profile.ca_cert {
+        println!("\tca_cert: {}", path.display());
+    }
+    if let Some(path) = &profile.client_cert {
+        println!("\tclient_cert: {}", path.display());
+    }
+    if let Some(path) = &profile.client_key {
+        println!("\tclient_key: {}", path.display());
+    }
+}
+
+/// Lists all profiles
+pub fn list(ctx: &Context) -> Result<(), CliError> {
+    let (path, config) = load_config(ctx)?;
+
+    let profiles: Vec<_> = config
+        .profiles
+        .keys()
+        .map(|name| {
+            json!({
+                "name": name,
+                "default": config.default_profile.as_ref() == Some(name),
+            })
+        })
+        .collect();
+
+    print_result(ctx, &profiles, |profiles| {
+        println!("Profiles in {}:", path.display());
+        if profiles.is_empty() {
+            println!("\tNo profiles");
+        }
+        for p in profiles {
+            if p["default"].as_bool().unwrap_or(false) {
+                println!("\t{} (default)", p["name"].as_str().unwrap_or_default());
+            } else {
+                println!("\t{}", p["name"].as_str().unwrap_or_default());
+            }
+        }
+    });
+
+    Ok(())
+}
+
+impl Processor for ConfigAddArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let (path, mut config) = load_config(ctx)?;
+
+        let profile = Profile {
+            base_url: self.profile_base_url.clone(),
+            api_key: self.read_api_key()?,
+            connect_timeout: self.profile_connect_timeout,
+            timeout: self.profile_timeout,
+            ca_cert: self.profile_ca_cert.clone(),
+            client_cert: self.profile_client_cert.clone(),
+            client_key: self.profile_client_key.clone(),
+        };
+        config.profiles.insert(self.name.clone(), profile);
+
+        if self.default || config.default_profile.is_none() {
+            config.default_profile = Some(self.name.clone());
+        }
+
+        save_config(&path, &config)?;
+
+        print_result(ctx, &json!({ "name": self.name }), |_| {
+            println!("Profile {} saved", self.name)
+        });
+        Ok(())
+    }
+}
+
+impl Processor for ConfigRemoveArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let (path, mut config) = load_config(ctx)?;
+
+        if config.profiles.remove(&self.name).is_none() {
+            return Err(CliError::Usage(format!("Profile {} not found", self.name)));
+        }
+
+        if config.default_profile.as_ref() == Some(&self.name) {
+            config.default_profile = None;
+        }
+
+        save_config(&path, &config)?;
+
+        print_result(ctx, &json!({ "name": self.name }), |_| {
+            println!("Profile {} removed", self.name)
+        });
+        Ok(())
+    }
+}
+
+/// Shows active profile
+pub fn show(ctx: &Context) -> Result<(), CliError> {
+    let (_, config) = load_config(ctx)?;
+
+    let name = config.active_profile_name(ctx.global.profile.as_deref());
+    let profile = name.and_then(|n| config.profiles.get(n));
+
+    // Profile is not applied to config commands, see `Command::is_local`
+    let mut global = ctx.global.clone();
+    global.apply_profile(&profile.cloned().unwrap_or_default())?;
+    let api_base = global.get_api_base();
+
+    let doc = json!({
+        "name": name,
+        "profile": profile.map(|p| Profile {
+            api_key: p.api_key.as_deref().map(mask_secret),
+            ..p.clone()
+        }),
+        "api_base": api_base,
+    });
+
+    print_result(ctx, &doc, |_| {
+        match name {
+            Some(name) => {
+                println!("Active profile: {}", name);
+                if let Some(profile) = profile {
+                    print_profile(profile);
+                }
+            }
+            None => println!("No active profile"),
+        }
+        println!("Effective API base: {}", api_base);
+    });
+
+    Ok(())
+}
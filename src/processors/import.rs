@@ -0,0 +1,116 @@
+use crate::{
+    cli::*,
+    error::CliError,
+    import::{self, ImportFormat},
+    processors::{caches::created_record, print_result, record},
+};
+
+use super::{Context, Processor};
+
+impl Processor for CacheImportArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let format = match self.format.or_else(|| ImportFormat::detect(&self.file)) {
+            Some(f) => f,
+            None => {
+                return Err(CliError::Usage(format!(
+                    "Unknown format of {}, use --format",
+                    self.file.display()
+                )))
+            }
+        };
+
+        let rows = import::read_file(&self.file, format, &self.mappings)?;
+
+        if ctx.global.dry_run {
+            let preview: Vec<_> = rows
+                .iter()
+                .map(|r| match &r.cache {
+                    Ok(c) => json!({ "row": r.row, "cache": c, "error": null }),
+                    Err(e) => json!({ "row": r.row, "cache": null, "error": e }),
+                })
+                .collect();
+            let invalid = rows.iter().filter(|r| r.cache.is_err()).count();
+
+            print_result(ctx, &preview, |_| {
+                println!("Import preview of {}:", self.file.display());
+                for r in &rows {
+                    match &r.cache {
+                        Ok(c) => {
+                            println!("Row {}: {}, {}", r.row, c.lat, c.long);
+                            println!("\tdescrip: {}", c.descrip);
+                            println!("\thint: {}", c.hint);
+                        }
+                        Err(e) => println!("Row {}: invalid: {}", r.row, e),
+                    }
+                }
+                println!(
+                    "{} caches to create, {} invalid rows",
+                    rows.len() - invalid,
+                    invalid
+                );
+            });
+
+            if invalid > 0 {
+                return Err(CliError::Partial(format!("{} rows are invalid", invalid)));
+            }
+            return Ok(());
+        }
+
+        let mut created = Vec::new();
+        let mut failed = Vec::new();
+        for r in rows {
+            let res = r.cache.and_then(|c| {
+                // Dry run is previewed above, so requests are sent here
+                let cache = ctx
+                    .client
+                    .create_cache(&c)
+                    .map_err(|e| e.to_string())?
+                    .sent()
+                    .ok_or_else(|| "Request is not sent in dry-run mode".to_string())?;
+                record(ctx, created_record(&c, &cache));
+                Ok(cache)
+            });
+
+            match res {
+                Ok(cache) => created.push((r.row, cache.id)),
+                Err(e) => {
+                    failed.push((r.row, e));
+                    if self.fail_fast {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let doc = json!({
+            "created": created
+                .iter()
+                .map(|(row, id)| json!({ "row": row, "id": id }))
+                .collect::<Vec<_>>(),
+            "failed": failed
+                .iter()
+                .map(|(row, e)| json!({ "row": row, "error": e }))
+                .collect::<Vec<_>>(),
+        });
+
+        print_result(ctx, &doc, |_| {
+            let ids: Vec<_> = created.iter().map(|(_, id)| id.to_string()).collect();
+            println!("Created {} caches: {}", created.len(), ids.join(", "));
+            if !failed.is_empty() {
+                println!("Failed {} rows:", failed.len());
+                for (row, e) in &failed {
+                    println!("\tRow {}: {}", row, e);
+                }
+            }
+        });
+
+        if !failed.is_empty() {
+            return Err(CliError::Partial(format!(
+                "{} rows failed to import",
+                failed.len()
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use msd_client::{Cache, CacheChanges, NewCache, User, UserChanges};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    cli::*,
+    error::CliError,
+    journal::{Entry, Journal, Operation, Record},
+    processors::{
+        caches::{changed_record, created_record, deleted_record},
+        fetch_before, print_result, record, sent,
+    },
+};
+
+use super::{Context, Processor};
+
+impl Processor for HistoryArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let mut entries = Journal::open(ctx.global)?.entries()?;
+        if let Some(limit) = self.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+
+        print_result(ctx, &entries, |entries| {
+            if entries.is_empty() {
+                println!("No changes recorded");
+            }
+            for e in entries {
+                let target = match (e.record.target, e.record.nmb) {
+                    (Some(id), Some(nmb)) => format!(" {} key #{}", id, nmb),
+                    (Some(id), None) => format!(" {}", id),
+                    _ => String::new(),
+                };
+                let undoes = match e.record.undoes {
+                    Some(id) => format!(" (undo of #{})", id),
+                    None => String::new(),
+                };
+                println!(
+                    "#{} {} {}{}{}",
+                    e.id,
+                    e.timestamp,
+                    e.record.operation.name(),
+                    target,
+                    undoes
+                );
+                match &e.profile {
+                    Some(p) => println!(
+                        "\t{} {}{} (profile {})",
+                        e.record.method, e.api_base, e.record.endpoint, p
+                    ),
+                    None => println!("\t{} {}{}", e.record.method, e.api_base, e.record.endpoint),
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Processor for UndoArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let entries = Journal::open(ctx.global)?.entries()?;
+        let entry = entries
+            .iter()
+            .find(|e| e.id == self.entry)
+            .ok_or_else(|| CliError::Usage(format!("No journal entry #{}", self.entry)))?;
+
+        if let Some(undo) = entries.iter().find(|e| e.record.undoes == Some(entry.id)) {
+            return Err(CliError::Usage(format!(
+                "Entry #{} is already undone by #{}",
+                entry.id, undo.id
+            )));
+        }
+        let api_base = ctx.global.get_api_base();
+        if entry.api_base != api_base {
+            return Err(CliError::Usage(format!(
+                "Entry #{} was made on {}, not on {}",
+                entry.id, entry.api_base, api_base
+            )));
+        }
+
+        let Some((id, undo)) = revert(ctx, entry)? else {
+            return Ok(());
+        };
+        let new_entry = record(ctx, undo.undoes(entry.id));
+
+        let doc = json!({
+            "undone": entry.id,
+            "entry": new_entry.map(|e| e.id),
+            "id": id,
+        });
+        print_result(ctx, &doc, |_| {
+            println!("Undone #{} {}", entry.id, entry.record.operation.name());
+            if entry.record.operation == Operation::CacheDelete {
+                println!("Cache re-created with new ID {}", id);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Sends inverse request of entry. Returns ID of changed object and record of
+/// the request, or `None` when the request is not sent in dry-run mode.
+fn revert(ctx: &Context, entry: &Entry) -> Result<Option<(i32, Record)>, CliError> {
+    let target = || {
+        entry
+            .record
+            .target
+            .ok_or_else(|| CliError::Local(format!("Entry #{} has no target ID", entry.id)))
+    };
+
+    match entry.record.operation {
+        Operation::CacheCreate => {
+            let id = target()?;
+            let before = fetch_before(ctx, |c| c.get_cache(id))?;
+            if sent(ctx, ctx.client.delete_cache(id)?).is_none() {
+                return Ok(None);
+            }
+            Ok(Some((id, deleted_record(id, before))))
+        }
+        Operation::CacheChange => {
+            let id = target()?;
+            let old: Cache = snapshot(entry)?;
+            let changes = CacheChanges {
+                lat: Some(old.lat),
+                long: Some(old.long),
+                descrip: Some(old.descrip),
+                hint: Some(old.hint),
+            };
+            let before = fetch_before(ctx, |c| c.get_cache(id))?;
+            if sent(ctx, ctx.client.update_cache(id, &changes)?).is_none() {
+                return Ok(None);
+            }
+            Ok(Some((id, changed_record(id, &changes, before))))
+        }
+        Operation::CacheDelete => {
+            // Server assigns a new ID, owner is the user of current api key
+            let old: Cache = snapshot(entry)?;
+            let new_cache = NewCache {
+                lat: old.lat,
+                long: old.long,
+                descrip: old.descrip,
+                hint: old.hint,
+            };
+            let Some(cache) = sent(ctx, ctx.client.create_cache(&new_cache)?) else {
+                return Ok(None);
+            };
+            Ok(Some((cache.id, created_record(&new_cache, &cache))))
+        }
+        Operation::UserChange => {
+            let id = target()?;
+            let old: User = snapshot(entry)?;
+            if !entry.record.request["password"].is_null() {
+                eprintln!(
+                    "Warning: password of user {} can not be restored, change it with `user change`",
+                    id
+                );
+            }
+            let changes = UserChanges {
+                email: Some(old.email),
+                password: None,
+            };
+            let before = fetch_before(ctx, |c| c.get_user(id))?;
+            if sent(ctx, ctx.client.update_user(id, &changes)?).is_none() {
+                return Ok(None);
+            }
+            let undo = Record::new(
+                Operation::UserChange,
+                "PUT",
+                format!("/user/{}", id),
+                Some(id),
+            )
+            .request(json!(changes))
+            .before(before);
+            Ok(Some((id, undo)))
+        }
+        Operation::KeyGenerate => {
+            let id = target()?;
+            let nmb = entry.record.nmb.ok_or_else(|| {
+                CliError::Local(format!("Number of key of entry #{} is unknown", entry.id))
+            })?;
+            if sent(ctx, ctx.client.revoke_key(id, nmb)?).is_none() {
+                return Ok(None);
+            }
+            let undo = Record {
+                nmb: Some(nmb),
+                ..Record::new(
+                    Operation::KeyRevoke,
+                    "DELETE",
+                    format!("/user/{}/keys/{}", id, nmb),
+                    Some(id),
+                )
+            };
+            Ok(Some((id, undo)))
+        }
+        Operation::UserCreate | Operation::KeyRevoke => Err(CliError::Usage(format!(
+            "{} can not be undone",
+            entry.record.operation.name()
+        ))),
+    }
+}
+
+/// State of object before the change of entry
+fn snapshot<T: DeserializeOwned>(entry: &Entry) -> Result<T, CliError> {
+    entry
+        .record
+        .before
+        .clone()
+        .and_then(|b| serde_json::from_value(b).ok())
+        .ok_or_else(|| CliError::Local(format!("Entry #{} has no valid snapshot", entry.id)))
+}
@@ -1,114 +1,101 @@
-use reqwest::blocking::Client;
-
-use crate::{cli::*, processors::basic_server_response_check};
-
-use super::{Processor, ProcessorErrorStatus};
-
-pub struct KeysCreateProcessor;
-impl Processor for KeysCreateProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::User(user_args) = &args.command {
-            if let UserCommand::Keys(key_args) = &user_args.command {
-                if let UserKeysCommand::Generate(cmd_args) = &key_args.command {
-                    let api_path = args.get_api_base();
-                    let req_url = format!("{}/user/{}/keys", api_path, cmd_args.id);
-
-                    let res = client.post(req_url).send();
-
-                    let json = basic_server_response_check(res, args)?;
-
-                    println!("Key created");
-                    println!(
-                        "Use your new API key: {}",
-                        json.get("key").unwrap().as_str().unwrap()
-                    );
-                    return Ok(());
-                }
-            }
-        }
-
-        Err(ProcessorErrorStatus::NotMyCommand)
+use crate::{
+    cli::*,
+    error::CliError,
+    journal::{Operation, Record},
+    processors::{print_result, record, sent},
+    table::{default_cell, TableSpec},
+};
+
+use super::{Context, Processor};
+
+impl Processor for UserKeysGenerateArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let Some(key) = sent(ctx, ctx.client.generate_key(self.id)?) else {
+            return Ok(());
+        };
+
+        // Number of key is needed to revoke it on undo, the key itself is not recorded
+        let nmb = ctx
+            .client
+            .list_keys(self.id)
+            .ok()
+            .and_then(|keys| keys.into_iter().find(|k| k.api_key == key))
+            .map(|k| k.nmb);
+        record(
+            ctx,
+            Record {
+                nmb,
+                ..Record::new(
+                    Operation::KeyGenerate,
+                    "POST",
+                    format!("/user/{}/keys", self.id),
+                    Some(self.id),
+                )
+            },
+        );
+
+        print_result(ctx, &json!({ "key": key }), |_| {
+            println!("Key created");
+            println!("Use your new API key: {}", key);
+        });
+        Ok(())
     }
 }
 
-pub struct KeysViewProcessor;
-impl Processor for KeysViewProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::User(user_args) = &args.command {
-            if let UserCommand::Keys(key_args) = &user_args.command {
-                if let UserKeysCommand::View(cmd_args) = &key_args.command {
-                    let api_path = args.get_api_base();
-                    let mut req_url = format!("{}/user/{}/keys", api_path, cmd_args.id);
-                    let mut one_view = false;
-
-                    if let Some(nmb) = cmd_args.nmb {
-                        req_url = format!("{}/{}", req_url, nmb);
-                        one_view = true;
-                    }
-
-                    let res = client.get(req_url).send();
-                    let json = basic_server_response_check(res, args)?;
-
-                    println!("Key found");
-                    if one_view {
-                        println!("API key: {}", json.get("key").unwrap().as_str().unwrap());
-                    } else {
-                        let keys_value = json
-                            .get("keys")
-                            .expect("Server error: keys not found")
-                            .as_array()
-                            .expect("Server error: Keys is not array");
-
-                        for json_key in keys_value {
-                            println!(
-                                "Key #{}: {}",
-                                json_key.get("nmb").unwrap().as_u64().unwrap(),
-                                json_key.get("api_key").unwrap().as_str().unwrap(),
-                            )
-                        }
-                    }
+static KEY_TABLE: TableSpec = TableSpec {
+    columns: &["nmb", "api_key"],
+    truncated: None,
+};
+
+impl Processor for UserKeysViewArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let mut keys = match self.nmb {
+            Some(nmb) => vec![ctx.client.get_key(self.id, nmb)?],
+            None => ctx.client.list_keys(self.id)?,
+        };
+        self.table.sort(&KEY_TABLE, &mut keys)?;
+
+        print_result(ctx, &keys, |keys| {
+            if self.nmb.is_none() && self.table.is_table() && !keys.is_empty() {
+                return self.table.print(&KEY_TABLE, keys, default_cell);
+            }
 
-                    return Ok(());
+            println!("Key found");
+            if self.nmb.is_some() {
+                println!("API key: {}", keys[0].api_key);
+            } else {
+                for key in keys {
+                    println!("Key #{}: {}", key.nmb, key.api_key)
                 }
             }
-        }
+        });
 
-        Err(ProcessorErrorStatus::NotMyCommand)
+        Ok(())
     }
 }
 
-pub struct KeysRevokeProcessor;
-impl Processor for KeysRevokeProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::User(user_args) = &args.command {
-            if let UserCommand::Keys(key_args) = &user_args.command {
-                if let UserKeysCommand::Revoke(cmd_args) = &key_args.command {
-                    let api_path = args.get_api_base();
-                    let req_url =
-                        format!("{}/user/{}/keys/{}", api_path, cmd_args.id, cmd_args.nmb);
-
-                    let res = client.delete(req_url).send();
-                    let _ = basic_server_response_check(res, args)?;
-
-                    println!("Key deleted");
-
-                    return Ok(());
-                }
-            }
+impl Processor for UserKeysDeleteArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        if sent(ctx, ctx.client.revoke_key(self.id, self.nmb)?).is_none() {
+            return Ok(());
         }
-
-        Err(ProcessorErrorStatus::NotMyCommand)
+        record(
+            ctx,
+            Record {
+                nmb: Some(self.nmb),
+                ..Record::new(
+                    Operation::KeyRevoke,
+                    "DELETE",
+                    format!("/user/{}/keys/{}", self.id, self.nmb),
+                    Some(self.id),
+                )
+            },
+        );
+
+        print_result(ctx, &json!({ "id": self.id, "nmb": self.nmb }), |_| {
+            println!("Key deleted")
+        });
+
+        Ok(())
     }
 }
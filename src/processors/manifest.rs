@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use msd_client::{ClientError, Outcome};
+
+use crate::{
+    cli::*,
+    error::CliError,
+    manifest::{Action, ActionKind, Manifest, Plan, State},
+    processors::{
+        caches::{changed_record, created_record, deleted_record},
+        confirm, print_dry_runs, print_result, record,
+    },
+};
+
+use super::{Context, Processor};
+
+/// Manifest compared with server
+struct Comparison {
+    plan: Plan,
+    state: State,
+    /// Names in state whose caches are gone from server
+    stale: Vec<String>,
+}
+
+/// Loads manifest and state and fetches caches of state from server
+fn compare(ctx: &Context, args: &ManifestArgs) -> Result<Comparison, CliError> {
+    let manifest = Manifest::load(&args.file)?;
+    let state_path = args.state_path();
+    let state = State::load(&state_path)?;
+
+    let api_base = ctx.global.get_api_base();
+    if let Some(state_base) = &state.api_base {
+        if *state_base != api_base {
+            return Err(CliError::Usage(format!(
+                "State {} belongs to {}, not to {}",
+                state_path.display(),
+                state_base,
+                api_base
+            )));
+        }
+    }
+
+    let mut current = BTreeMap::new();
+    let mut stale = Vec::new();
+    for (name, &id) in &state.caches {
+        match ctx.client.get_cache(id) {
+            Ok(cache) => {
+                current.insert(name.clone(), cache);
+            }
+            Err(ClientError::Server { status, .. } | ClientError::HttpStatus(status))
+                if status.as_u16() == 404 =>
+            {
+                stale.push(name.clone())
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(Comparison {
+        plan: Plan::new(&manifest, &current),
+        state,
+        stale,
+    })
+}
+
+fn print_plan(plan: &Plan) {
+    if plan.is_empty() {
+        println!("No changes, caches match manifest");
+        return;
+    }
+
+    for action in &plan.actions {
+        let sign = match action.kind {
+            ActionKind::Create => '+',
+            ActionKind::Change => '~',
+            ActionKind::Delete => '-',
+        };
+        match action.id {
+            Some(id) => println!("  {} {} (cache {})", sign, action.name, id),
+            None => println!("  {} {}", sign, action.name),
+        }
+        for f in &action.fields {
+            match (&f.before, &f.after) {
+                (Some(before), Some(after)) => println!("\t{}: {} -> {}", f.field, before, after),
+                (None, Some(value)) | (Some(value), None) => println!("\t{}: {}", f.field, value),
+                (None, None) => {}
+            }
+        }
+    }
+    println!();
+    println!(
+        "Plan: {} to create, {} to change, {} to delete",
+        plan.count(ActionKind::Create),
+        plan.count(ActionKind::Change),
+        plan.count(ActionKind::Delete)
+    );
+}
+
+impl Processor for CachePlanArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let comparison = compare(ctx, &self.manifest)?;
+
+        print_result(ctx, &comparison.plan.actions, |_| {
+            print_plan(&comparison.plan)
+        });
+        Ok(())
+    }
+}
+
+impl Processor for CacheApplyArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let Comparison {
+            plan,
+            mut state,
+            stale,
+        } = compare(ctx, &self.manifest)?;
+        let state_path = self.manifest.state_path();
+
+        if ctx.global.output.is_text() {
+            print_plan(&plan);
+        }
+
+        // Nothing is sent in dry-run mode, requests are printed instead
+        if !plan.is_empty() && !self.yes && !ctx.global.dry_run {
+            let prompt = format!(
+                "Apply {} changes to {}?",
+                plan.actions.len(),
+                ctx.global.get_api_base()
+            );
+            if !confirm(&prompt)? {
+                return Err(CliError::Usage("Apply cancelled".to_string()));
+            }
+        }
+
+        for name in &stale {
+            state.caches.remove(name);
+        }
+
+        let mut applied = Vec::new();
+        let mut failed = Vec::new();
+        let mut not_sent = Vec::new();
+        for action in &plan.actions {
+            match apply(ctx, action) {
+                Ok(Outcome::Sent(id)) => {
+                    match action.kind {
+                        ActionKind::Delete => state.caches.remove(&action.name),
+                        _ => state.caches.insert(action.name.clone(), id),
+                    };
+                    applied.push(json!({ "action": action.kind, "name": action.name, "id": id }));
+                }
+                Ok(Outcome::Prepared(request)) => not_sent.push(request),
+                Err(e) => failed.push(json!({
+                    "action": action.kind,
+                    "name": action.name,
+                    "error": e.to_string(),
+                })),
+            }
+        }
+
+        if ctx.global.dry_run {
+            print_dry_runs(ctx, &not_sent);
+            return Ok(());
+        }
+
+        if !applied.is_empty() || !stale.is_empty() {
+            state.api_base = Some(ctx.global.get_api_base());
+            state.save(&state_path)?;
+        }
+
+        let doc = json!({ "applied": applied, "failed": failed });
+        print_result(ctx, &doc, |_| {
+            if !plan.is_empty() {
+                println!(
+                    "Applied {} of {} changes",
+                    applied.len(),
+                    plan.actions.len()
+                );
+            }
+            for f in &failed {
+                println!(
+                    "\tFailed to {} {}: {}",
+                    f["action"].as_str().unwrap_or_default(),
+                    f["name"].as_str().unwrap_or_default(),
+                    f["error"].as_str().unwrap_or_default()
+                );
+            }
+        });
+
+        if !failed.is_empty() {
+            return Err(CliError::Partial(format!(
+                "{} changes failed to apply",
+                failed.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Sends request of action. Returns ID of cache.
+fn apply(ctx: &Context, action: &Action) -> Result<Outcome<i32>, CliError> {
+    match (action.kind, action.id, &action.desired) {
+        (ActionKind::Create, _, Some(desired)) => {
+            Ok(ctx.client.create_cache(desired)?.map(|cache| {
+                record(ctx, created_record(desired, &cache));
+                cache.id
+            }))
+        }
+        (ActionKind::Change, Some(id), _) => {
+            let changes = action.changes();
+            Ok(ctx.client.update_cache(id, &changes)?.map(|_| {
+                record(ctx, changed_record(id, &changes, json!(action.current)));
+                id
+            }))
+        }
+        (ActionKind::Delete, Some(id), _) => Ok(ctx.client.delete_cache(id)?.map(|_| {
+            record(ctx, deleted_record(id, json!(action.current)));
+            id
+        })),
+        _ => Err(CliError::Local(format!(
+            "Invalid action of {}",
+            action.name
+        ))),
+    }
+}
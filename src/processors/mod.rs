@@ -1,121 +1,212 @@
-use reqwest::blocking::{Client, Response};
-use serde_json::Value;
-
-use crate::cli::MainCliArgs;
-
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+};
+
+use msd_client::{ClientError, MsdClient, Outcome, PreparedRequest};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    cli::{CacheCommand, Command, ConfigCommand, GlobalArgs, UserCommand, UserKeysCommand},
+    error::CliError,
+    journal::{Entry, Journal, Record},
+    output::print_document,
+    secrets,
+};
+
+mod auth;
 mod caches;
+mod completions;
+mod config;
+mod import;
+mod journal;
 mod keys;
+mod manifest;
 mod users;
 
-pub enum ProcessorErrorStatus {
-    NotMyCommand, // Processor gives control to next processor
-    Error,        // Processor processed command but some errors ocurs. Stop a application.
+/// State shared by all commands
+pub struct Context<'a> {
+    pub global: &'a GlobalArgs,
+    pub client: &'a MsdClient,
+    /// Result document of command run by batch. Batch prints it in its
+    /// summary, so structured formats are not printed by command itself.
+    pub captured: Option<&'a RefCell<Option<Value>>>,
 }
 
+/// Command implemented by its arguments struct
 pub trait Processor {
-    fn process_args(
-        &self,
-        args: &MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus>;
+    fn process(&self, ctx: &Context) -> Result<(), CliError>;
 }
 
-pub fn init_processors() -> Vec<Box<dyn Processor>> {
-    vec![
-        // Users
-        Box::new(users::UserCreateProcessor {}),
-        Box::new(users::UserViewProcessor {}),
-        Box::new(users::UserChangeProcessor {}),
-        // Keys
-        Box::new(keys::KeysCreateProcessor {}),
-        Box::new(keys::KeysViewProcessor {}),
-        Box::new(keys::KeysRevokeProcessor {}),
-        // Caches
-        Box::new(caches::CacheCreateProcessor {}),
-        Box::new(caches::CacheFindProcessor {}),
-        Box::new(caches::CacheViewProcessor {}),
-        Box::new(caches::CacheChangeProcessor {}),
-        Box::new(caches::CacheDeleteProcessor {}),
-        // MUST BE ALWAYS LAST
-        Box::new(NotProcessedCommand {}),
-    ]
+/// Runs command. Every command must be handled here.
+pub fn process_command(command: &Command, ctx: &Context) -> Result<(), CliError> {
+    match command {
+        Command::User(user_args) => match &user_args.command {
+            UserCommand::Create(cmd_args) => cmd_args.process(ctx),
+            UserCommand::View(cmd_args) => cmd_args.process(ctx),
+            UserCommand::Change(cmd_args) => cmd_args.process(ctx),
+            UserCommand::Keys(key_args) => match &key_args.command {
+                UserKeysCommand::Generate(cmd_args) => cmd_args.process(ctx),
+                UserKeysCommand::View(cmd_args) => cmd_args.process(ctx),
+                UserKeysCommand::Revoke(cmd_args) => cmd_args.process(ctx),
+            },
+        },
+        Command::Cache(cache_args) => match &cache_args.command {
+            CacheCommand::Create(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::Find(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::View(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::Change(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::Delete(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::Import(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::Plan(cmd_args) => cmd_args.process(ctx),
+            CacheCommand::Apply(cmd_args) => cmd_args.process(ctx),
+        },
+        Command::Config(config_args) => match &config_args.command {
+            ConfigCommand::List => config::list(ctx),
+            ConfigCommand::Add(cmd_args) => cmd_args.process(ctx),
+            ConfigCommand::Remove(cmd_args) => cmd_args.process(ctx),
+            ConfigCommand::Show => config::show(ctx),
+        },
+        Command::Login(cmd_args) => cmd_args.process(ctx),
+        Command::Logout => auth::logout(ctx),
+        Command::Whoami => auth::whoami(ctx),
+        Command::History(cmd_args) => cmd_args.process(ctx),
+        Command::Undo(cmd_args) => cmd_args.process(ctx),
+        Command::Completions(cmd_args) => cmd_args.process(ctx),
+        Command::Man(cmd_args) => cmd_args.process(ctx),
+        Command::CompleteIds(cmd_args) => cmd_args.process(ctx),
+        Command::Shell(_) => Err(CliError::Usage("Shell is already running".to_string())),
+        Command::Batch(_) => Err(CliError::Usage(
+            "Batch can not be run from shell or batch".to_string(),
+        )),
+        Command::MockServer(_) => Err(CliError::Usage(
+            "Mock server can not be started from shell".to_string(),
+        )),
+    }
 }
 
-pub struct NotProcessedCommand;
-impl Processor for NotProcessedCommand {
-    fn process_args(&self, _: &MainCliArgs, _: &mut Client) -> Result<(), ProcessorErrorStatus> {
-        println!("No processor avaiable for command. Report a bug.");
-        return Err(ProcessorErrorStatus::Error);
+/// Value read from response to request changing data. In dry-run mode the
+/// request is printed as result of command and `None` is returned.
+pub fn sent<T>(ctx: &Context, outcome: Outcome<T>) -> Option<T> {
+    match outcome {
+        Outcome::Sent(value) => Some(value),
+        Outcome::Prepared(request) => {
+            print_dry_run(ctx, &request);
+            None
+        }
     }
 }
 
-pub fn print_json_value(json_value: &Value) {
-    if json_value.is_object() {
-        let json_obj = json_value.as_object().unwrap();
-        let keys = json_obj.keys();
+/// Prints request of dry run with api key and passwords redacted
+fn print_dry_run(ctx: &Context, request: &PreparedRequest) {
+    print_result(ctx, &dry_run_doc(request), |_| {
+        println!("Dry run, request not sent:");
+        print_request(request);
+    });
+}
 
-        for k in keys {
-            println!("\t{}: {}", k, json_obj[k]);
+/// Prints all requests of dry run of command sending several ones
+pub fn print_dry_runs(ctx: &Context, requests: &[PreparedRequest]) {
+    let docs: Vec<_> = requests.iter().map(dry_run_doc).collect();
+    print_result(ctx, &docs, |_| {
+        println!("Dry run, {} requests not sent:", requests.len());
+        for request in requests {
+            println!();
+            print_request(request);
         }
-    } else {
-        println!("\t{}", json_value)
-    }
+    });
 }
 
-pub fn print_json_value_wo_error(json_value: &Value) {
-    if json_value.is_object() {
-        let json_obj = json_value.as_object().unwrap();
-        let keys = json_obj.keys();
+fn dry_run_doc(request: &PreparedRequest) -> Value {
+    let headers: Map<String, Value> = request
+        .headers
+        .iter()
+        .map(|(k, v)| (k.clone(), json!(secrets::redact_header(k, v))))
+        .collect();
+
+    json!({
+        "dry_run": true,
+        "method": request.method,
+        "url": request.url,
+        "headers": headers,
+        "body": request.body.as_ref().map(secrets::redact),
+    })
+}
 
-        for k in keys {
-            if k.eq("error") {
-                continue;
-            }
-            println!("\t{}: {}", k, json_obj[k]);
-        }
-    } else {
-        println!("\t{}", json_value)
+fn print_request(request: &PreparedRequest) {
+    println!("{} {}", request.method, request.url);
+    for (k, v) in &request.headers {
+        println!("{}: {}", k, secrets::redact_header(k, v));
+    }
+    if let Some(body) = &request.body {
+        println!();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&secrets::redact(body)).unwrap_or_default()
+        );
     }
 }
 
-pub fn check_server_error(json_value: &Value) -> Result<(), ProcessorErrorStatus> {
-    if let Some(v) = json_value.get("error") {
-        if v.is_boolean() && v.as_bool().unwrap() {
-            // Error
-            println!("Server returned a error!");
-            print_json_value_wo_error(&json_value);
+/// State of object before change for journal. Nothing is fetched in dry-run mode.
+pub fn fetch_before<T: Serialize>(
+    ctx: &Context,
+    fetch: impl FnOnce(&MsdClient) -> Result<T, ClientError>,
+) -> Result<Value, CliError> {
+    if ctx.global.dry_run {
+        return Ok(Value::Null);
+    }
+    Ok(json!(fetch(ctx.client)?))
+}
 
-            return Err(ProcessorErrorStatus::Error);
+/// Appends change made on server to journal. Change is already done, so
+/// failure to write journal is only reported.
+pub fn record(ctx: &Context, record: Record) -> Option<Entry> {
+    let res = Journal::open(ctx.global).and_then(|j| j.append(ctx.global, record));
+    match res {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            eprintln!("Warning: change is not recorded: {}", e);
+            None
         }
     }
-
-    return Ok(());
 }
 
-pub fn basic_server_response_check(
-    resp_res: Result<Response, reqwest::Error>,
-    args: &MainCliArgs,
-) -> Result<Value, ProcessorErrorStatus> {
-    if let Err(e) = resp_res {
-        println!("Error: {:?}", e);
-        return Err(ProcessorErrorStatus::Error);
-    }
+/// Asks on stderr to type `yes` and reads answer from stdin
+pub fn confirm(prompt: &str) -> Result<bool, CliError> {
+    eprint!("{} Only 'yes' is accepted: ", prompt);
+    let _ = io::stderr().flush();
 
-    let response = resp_res.unwrap();
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| CliError::Local(format!("Failed to read answer: {}", e)))?;
+    Ok(answer.trim() == "yes")
+}
 
-    let json_value_res = response.json::<Value>();
-    if let Err(e) = json_value_res {
-        println!("Server returned invalid JSON: {:?}", e);
-        return Err(ProcessorErrorStatus::Error);
+/// Prints result of command. Human readable printer is used only in text mode,
+/// otherwise `doc` is printed in requested format.
+pub fn print_result<T: Serialize>(ctx: &Context, doc: &T, human: impl FnOnce(&T)) {
+    if let Some(captured) = ctx.captured {
+        captured.replace(Some(json!(doc)));
     }
 
-    let json_value = json_value_res.unwrap();
-
-    if args.verbose {
-        println!("Server response: \n{:#?}", json_value);
+    if ctx.global.output.is_text() {
+        human(doc);
+    } else if ctx.captured.is_none() {
+        print_document(ctx.global.output, &json!(doc));
     }
+}
 
-    check_server_error(&json_value)?;
+pub fn print_json_value(json_value: &Value) {
+    if json_value.is_object() {
+        let json_obj = json_value.as_object().unwrap();
+        let keys = json_obj.keys();
 
-    return Ok(json_value);
+        for k in keys {
+            println!("\t{}: {}", k, json_obj[k]);
+        }
+    } else {
+        println!("\t{}", json_value)
+    }
 }
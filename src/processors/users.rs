@@ -1,98 +1,79 @@
-use reqwest::blocking::Client;
-use serde_json::json;
+use msd_client::{NewUser, UserChanges};
 
 use crate::{
     cli::*,
-    processors::{basic_server_response_check, print_json_value_wo_error},
+    error::CliError,
+    journal::{Operation, Record},
+    processors::{fetch_before, print_json_value, print_result, record, sent},
 };
 
-use super::{Processor, ProcessorErrorStatus};
-
-pub struct UserCreateProcessor;
-impl Processor for UserCreateProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::User(user_args) = &args.command {
-            if let UserCommand::Create(cmd_args) = &user_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/user/", api_path);
-
-                let res = client
-                    .post(req_url)
-                    .json(&json!(
-                        {
-                            "login": cmd_args.name,
-                            "email": cmd_args.email,
-                            "password": cmd_args.password,
-                        }
-                    ))
-                    .send();
-
-                let json = basic_server_response_check(res, args)?;
-
-                println!("User created");
-                println!(
-                    "Use your default API key: {}",
-                    json.get("api_key").unwrap().as_str().unwrap()
-                );
-                return Ok(());
-            }
-        }
-
-        Err(ProcessorErrorStatus::NotMyCommand)
+use super::{Context, Processor};
+
+impl Processor for UserCreateArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let user = NewUser {
+            login: self.name.clone(),
+            email: self.email.clone(),
+            password: self.password.read(true)?.unwrap_or_default(),
+        };
+        let Some(created) = sent(ctx, ctx.client.create_user(&user)?) else {
+            return Ok(());
+        };
+        record(
+            ctx,
+            Record::new(
+                Operation::UserCreate,
+                "POST",
+                "/user/".to_string(),
+                created.id,
+            )
+            .request(json!(user))
+            .result(json!(created)),
+        );
+
+        print_result(ctx, &created, |created| {
+            println!("User created");
+            println!("Use your default API key: {}", created.api_key);
+        });
+        Ok(())
     }
 }
 
-pub struct UserViewProcessor;
-impl Processor for UserViewProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::User(user_args) = &args.command {
-            if let UserCommand::View(cmd_args) = &user_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/user/{}", api_path, cmd_args.id);
-
-                let res = client.get(req_url).send();
+impl Processor for UserViewArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let user = ctx.client.get_user(self.id)?;
 
-                let json = basic_server_response_check(res, args)?;
-
-                println!("User founded!");
-                print_json_value_wo_error(&json);
-                return Ok(());
-            }
-        }
-
-        Err(ProcessorErrorStatus::NotMyCommand)
+        print_result(ctx, &user, |user| {
+            println!("User founded!");
+            print_json_value(&json!(user));
+        });
+        Ok(())
     }
 }
 
-pub struct UserChangeProcessor;
-impl Processor for UserChangeProcessor {
-    fn process_args(
-        &self,
-        args: &crate::cli::MainCliArgs,
-        client: &mut Client,
-    ) -> Result<(), ProcessorErrorStatus> {
-        if let Command::User(user_args) = &args.command {
-            if let UserCommand::Change(cmd_args) = &user_args.command {
-                let api_path = args.get_api_base();
-                let req_url = format!("{}/user/{}", api_path, cmd_args.id);
-
-                let res = client.put(req_url).json(cmd_args).send();
-
-                let _ = basic_server_response_check(res, args)?;
-
-                println!("User changed");
-                return Ok(());
-            }
+impl Processor for UserChangeArgs {
+    fn process(&self, ctx: &Context) -> Result<(), CliError> {
+        let changes = UserChanges {
+            email: self.email.clone(),
+            password: self.password.read(false)?,
+        };
+        let before = fetch_before(ctx, |c| c.get_user(self.id))?;
+        if sent(ctx, ctx.client.update_user(self.id, &changes)?).is_none() {
+            return Ok(());
         }
-
-        Err(ProcessorErrorStatus::NotMyCommand)
+        record(
+            ctx,
+            Record::new(
+                Operation::UserChange,
+                "PUT",
+                format!("/user/{}", self.id),
+                Some(self.id),
+            )
+            .request(json!(changes))
+            .before(before),
+        );
+
+        print_result(ctx, &json!({ "id": self.id }), |_| println!("User changed"));
+        Ok(())
     }
 }
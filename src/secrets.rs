@@ -0,0 +1,158 @@
+//! Reading of passwords and API keys without exposing them on command line
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+pub use msd_client::{redact, redact_header};
+
+use crate::error::CliError;
+
+/// Environment variable with API key
+pub static API_KEY_ENV: &str = "MSD_API_KEY";
+
+fn non_empty(secret: String, source: &str) -> Result<String, CliError> {
+    if secret.is_empty() {
+        return Err(CliError::Usage(format!("Empty secret in {}", source)));
+    }
+    Ok(secret)
+}
+
+/// Reads first line of file
+pub fn read_file(path: &Path) -> Result<String, CliError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| CliError::Local(format!("Failed to read {}: {}", path.display(), e)))?;
+    let line = content.lines().next().unwrap_or_default().to_string();
+
+    non_empty(line, &path.display().to_string())
+}
+
+/// Reads first line of stdin
+pub fn read_stdin() -> Result<String, CliError> {
+    read_line(io::stdin().lock(), "stdin")
+}
+
+fn read_line(mut reader: impl BufRead, source: &str) -> Result<String, CliError> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| CliError::Local(format!("Failed to read {}: {}", source, e)))?;
+    let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
+
+    non_empty(line, source)
+}
+
+/// Asks password on terminal without echo. With `confirm` password is asked twice.
+pub fn prompt_password(confirm: bool) -> Result<String, CliError> {
+    let prompt_error = |e: io::Error| {
+        CliError::Usage(format!(
+            "Failed to prompt password ({}), use --password-stdin or --password-file",
+            e
+        ))
+    };
+
+    let password = rpassword::prompt_password("Password: ").map_err(prompt_error)?;
+    if password.is_empty() {
+        return Err(CliError::Usage("Password is empty".to_string()));
+    }
+
+    if confirm {
+        let repeated = rpassword::prompt_password("Repeat password: ").map_err(prompt_error)?;
+        if repeated != password {
+            return Err(CliError::Usage("Passwords do not match".to_string()));
+        }
+    }
+
+    Ok(password)
+}
+
+/// Asks api key on terminal without echo. `stdin_option` is suggested when
+/// there is no terminal.
+pub fn prompt_api_key(stdin_option: &str) -> Result<String, CliError> {
+    let key = rpassword::prompt_password("Api key: ").map_err(|e| {
+        CliError::Usage(format!(
+            "Failed to prompt api key ({}), use {}",
+            e, stdin_option
+        ))
+    })?;
+    non_empty(key, "api key")
+}
+
+/// Writes file readable and writable only by its owner, e.g. of api keys
+pub fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    // Mode of an existing file is not changed by open
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("msd-secret-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_first_line_of_stdin() {
+        assert_eq!(
+            read_line(&b"secret\r\nrest\n"[..], "stdin").unwrap(),
+            "secret"
+        );
+        assert_eq!(read_line(&b"secret"[..], "stdin").unwrap(), "secret");
+        // Spaces may be part of password
+        assert_eq!(
+            read_line(&b" pass word \n"[..], "stdin").unwrap(),
+            " pass word "
+        );
+
+        for empty in [&b""[..], &b"\n"[..], &b"\r\nsecret"[..]] {
+            let e = read_line(empty, "stdin").unwrap_err();
+            assert_eq!(e.exit_code(), 2);
+            assert_eq!(e.to_string(), "Empty secret in stdin");
+        }
+    }
+
+    #[test]
+    fn reads_first_line_of_file() {
+        let path = temp_file("key", "key1\r\nkey2\n");
+        let res = read_file(&path);
+        let empty = temp_file("empty", "\n");
+        let empty_res = read_file(&empty);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&empty).unwrap();
+
+        assert_eq!(res.unwrap(), "key1");
+        assert_eq!(empty_res.unwrap_err().exit_code(), 2);
+        assert_eq!(read_file(&path).unwrap_err().exit_code(), 1);
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        assert_eq!(redact_header("X-API-Key", "abc"), "<redacted>");
+        assert_eq!(redact_header("accept", "*/*"), "*/*");
+        assert_eq!(
+            redact(
+                &json!({ "user": { "password": "p", "email": "e" }, "keys": [{ "key": "k" }], "api_key": null })
+            ),
+            json!({ "user": { "password": "<redacted>", "email": "e" }, "keys": [{ "key": "<redacted>" }], "api_key": null })
+        );
+    }
+}
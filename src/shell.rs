@@ -0,0 +1,329 @@
+//! Interactive shell running commands with one client
+//!
+//! Lines are parsed like command line arguments without global options, which
+//! are taken from the session. Besides commands the shell understands:
+//! * `set api <key>` - use another api key, the line is not kept in history
+//! * `set profile <name>` - switch to profile from config file
+//! * `set output <format>` - change output format
+//! * `set` - show current settings
+//! * `exit`, `quit` or Ctrl-D - leave shell
+
+use std::{cell::RefCell, fs, path::Path};
+
+use clap::{ArgEnum, Command as ClapCommand, CommandFactory, StructOpt};
+use msd_client::MsdClient;
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    validate::Validator, Editor, Helper,
+};
+use serde_json::Value;
+
+use crate::{
+    cli::{Command, GlobalArgs, ShellArgs, ShellLine},
+    connection,
+    error::CliError,
+    output::OutputFormat,
+    processors::{self, Context},
+    secrets,
+};
+
+static HISTORY_DIR_NAME: &str = "msd-cli";
+static HISTORY_FILE_NAME: &str = "history";
+
+static BUILTINS: [&str; 4] = ["set", "exit", "quit", "help"];
+static SETTINGS: [&str; 3] = ["api", "profile", "output"];
+
+/// Runs shell until `exit` or end of input
+pub fn run(args: &ShellArgs, global: GlobalArgs) -> Result<(), CliError> {
+    let mut session = Session::new(global)?;
+
+    let mut editor = Editor::<ShellHelper>::new();
+    editor.set_helper(Some(ShellHelper {
+        command: ShellLine::command(),
+    }));
+
+    let history = args
+        .history_file
+        .clone()
+        .or_else(|| dirs::data_dir().map(|d| d.join(HISTORY_DIR_NAME).join(HISTORY_FILE_NAME)));
+    if let Some(path) = &history {
+        // History is missing on first start
+        let _ = editor.load_history(path);
+    }
+
+    loop {
+        let line = match editor.readline(&session.prompt()) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(CliError::Local(format!("Failed to read line: {}", e))),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !sets_api_key(line) {
+            editor.add_history_entry(line);
+        }
+
+        match session.execute(line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    if let Some(path) = &history {
+        save_history(&mut editor, path);
+    }
+    Ok(())
+}
+
+/// Line with api key, which is not kept in history
+fn sets_api_key(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next() == Some("set") && words.next() == Some("api")
+}
+
+fn save_history(editor: &mut Editor<ShellHelper>, path: &Path) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    // History is rewritten by editor, which keeps mode of existing file
+    let res = secrets::write_private(path, b"")
+        .map_err(ReadlineError::Io)
+        .and_then(|_| editor.save_history(path));
+    if let Err(e) = res {
+        eprintln!("Failed to save history to {}: {}", path.display(), e);
+    }
+}
+
+/// Global options and client of shell, also used by batch
+pub struct Session {
+    /// Options given on command line and changed with `set`
+    base: GlobalArgs,
+    /// Options with applied profile
+    global: GlobalArgs,
+    client: MsdClient,
+}
+
+impl Session {
+    pub fn new(base: GlobalArgs) -> Result<Self, CliError> {
+        let mut global = base.clone();
+        // Any command may be run in session
+        let client = connection::connect(&mut global, true)?;
+        Ok(Self {
+            base,
+            global,
+            client,
+        })
+    }
+
+    pub fn global(&self) -> &GlobalArgs {
+        &self.global
+    }
+
+    fn prompt(&self) -> String {
+        match &self.base.profile {
+            Some(name) => format!("msd ({})> ", name),
+            None => "msd> ".to_string(),
+        }
+    }
+
+    /// Executes line. Returns `false` when shell must exit.
+    fn execute(&mut self, line: &str) -> Result<bool, CliError> {
+        let words = shell_words::split(line).map_err(|e| CliError::Usage(e.to_string()))?;
+        if let Some(res) = self.builtin(&words) {
+            return res;
+        }
+
+        let line = match ShellLine::try_parse_from(&words) {
+            Ok(line) => line,
+            Err(e) => {
+                // Prints help as well as usage errors
+                let _ = e.print();
+                return Ok(true);
+            }
+        };
+
+        self.run(&line.command, None).map(|_| true)
+    }
+
+    /// Executes `exit`, `quit` or `set`. Returns `None` for other lines and
+    /// whether session goes on otherwise.
+    pub fn builtin(&mut self, words: &[String]) -> Option<Result<bool, CliError>> {
+        match words.first().map(String::as_str) {
+            Some("exit") | Some("quit") => Some(Ok(false)),
+            Some("set") => Some(self.set(&words[1..]).map(|_| true)),
+            _ => None,
+        }
+    }
+
+    /// Runs command with settings of session
+    pub fn run(
+        &self,
+        command: &Command,
+        captured: Option<&RefCell<Option<Value>>>,
+    ) -> Result<(), CliError> {
+        let ctx = Context {
+            global: &self.global,
+            client: &self.client,
+            captured,
+        };
+        processors::process_command(command, &ctx)
+    }
+
+    fn set(&mut self, words: &[String]) -> Result<(), CliError> {
+        let mut base = self.base.clone();
+
+        match words {
+            [] => {
+                println!("profile: {}", self.base.profile.as_deref().unwrap_or("-"));
+                println!("api base: {}", self.global.get_api_base());
+                println!(
+                    "api key: {}",
+                    if self.global.api.is_some() {
+                        "set"
+                    } else {
+                        "not set"
+                    }
+                );
+                println!("output: {:?}", self.global.output);
+                return Ok(());
+            }
+            [name, value] if name == "api" => {
+                base.api = Some(value.clone());
+                base.api_file = None;
+            }
+            [name, value] if name == "profile" => base.profile = Some(value.clone()),
+            [name, value] if name == "output" => {
+                base.output = OutputFormat::from_str(value, true).map_err(CliError::Usage)?;
+            }
+            _ => {
+                return Err(CliError::Usage(
+                    "Usage: set [api <key> | profile <name> | output <format>]".to_string(),
+                ))
+            }
+        }
+
+        // Session is kept unchanged when new settings do not work
+        *self = Session::new(base)?;
+        Ok(())
+    }
+}
+
+/// Completes commands, flags and their values from clap definitions
+struct ShellHelper {
+    command: ClapCommand<'static>,
+}
+
+impl ShellHelper {
+    fn candidates(&self, words: &[&str], word: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = match words {
+            [] => BUILTINS
+                .iter()
+                .map(|b| b.to_string())
+                .chain(subcommands(&self.command))
+                .collect(),
+            ["set"] => SETTINGS.iter().map(|s| s.to_string()).collect(),
+            ["set", "output"] => possible_values(OutputFormat::value_variants()),
+            ["set", ..] => Vec::new(),
+            _ => {
+                // Deepest subcommand mentioned on line
+                let mut cmd = &self.command;
+                for w in words {
+                    if let Some(sub) = cmd.find_subcommand(*w) {
+                        cmd = sub;
+                    }
+                }
+
+                let previous = words.last().and_then(|w| w.strip_prefix("--"));
+                let values = previous
+                    .and_then(|flag| cmd.get_arguments().find(|a| a.get_long() == Some(flag)))
+                    .and_then(|a| a.get_possible_values());
+
+                match values {
+                    Some(values) => values.iter().map(|v| v.get_name().to_string()).collect(),
+                    None if word.starts_with('-') => cmd
+                        .get_arguments()
+                        .filter(|a| !a.is_hide_set())
+                        .filter_map(|a| a.get_long())
+                        .map(|l| format!("--{}", l))
+                        .collect(),
+                    None => subcommands(cmd).collect(),
+                }
+            }
+        };
+
+        candidates.retain(|c| c.starts_with(word));
+        candidates.sort();
+        candidates
+    }
+}
+
+fn subcommands<'a>(cmd: &'a ClapCommand<'static>) -> impl Iterator<Item = String> + 'a {
+    cmd.get_subcommands()
+        .filter(|s| !s.is_hide_set())
+        .map(|s| s.get_name().to_string())
+        .filter(|name| name != "shell" && name != "batch")
+}
+
+fn possible_values<T: ArgEnum>(variants: &[T]) -> Vec<String> {
+    variants
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_string())
+        .collect()
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let words: Vec<&str> = before[..start].split_whitespace().collect();
+
+        Ok((start, self.candidates(&words, &before[start..])))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(line: &str) -> Vec<String> {
+        let helper = ShellHelper {
+            command: ShellLine::command(),
+        };
+        let start = line.rfind(' ').map_or(0, |i| i + 1);
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+        helper.candidates(&words, &line[start..])
+    }
+
+    #[test]
+    fn completes_from_clap_definitions() {
+        assert_eq!(complete("ca"), ["cache"]);
+        assert_eq!(complete("s"), ["set"]);
+        assert_eq!(complete("cache f"), ["find"]);
+        assert!(complete("cache find --").contains(&"--near".to_string()));
+        assert_eq!(complete("cache view --export g"), ["geojson", "gpx"]);
+        assert_eq!(complete("set output y"), ["yaml"]);
+    }
+}
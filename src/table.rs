@@ -0,0 +1,316 @@
+//! Aligned tables for list results
+//!
+//! Rows are JSON objects, the same ones printed with `--output json`, so
+//! columns are named after their fields. Sorting applies to all output
+//! formats and exports, column selection and layout only to text output.
+
+use std::{
+    cmp::Ordering,
+    io::{self, IsTerminal},
+};
+
+use clap::ArgEnum;
+use serde::Serialize;
+use serde_json::Value;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{cli::TableArgs, error::CliError};
+
+/// Gap between columns
+const SEPARATOR: &str = "  ";
+/// Truncated column is never made narrower
+const MIN_TRUNCATED_WIDTH: usize = 10;
+
+/// Layout of list results in text output
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Aligned table
+    Table,
+    /// Header and `key: value` lines for every item
+    List,
+    /// Table on terminal, list when stdout is redirected
+    Auto,
+}
+
+/// Columns of one kind of list results
+pub struct TableSpec<'a> {
+    /// All columns in default order
+    pub columns: &'a [&'a str],
+    /// Column shrunk to fit terminal width
+    pub truncated: Option<&'a str>,
+}
+
+impl TableArgs {
+    /// Whether text output is a table
+    pub fn is_table(&self) -> bool {
+        match self.layout {
+            Layout::Table => true,
+            Layout::List => false,
+            Layout::Auto => io::stdout().is_terminal(),
+        }
+    }
+
+    /// Checks requested columns and sorts `items` by `--sort-by` and `--reverse`
+    pub fn sort<T: Serialize>(&self, spec: &TableSpec, items: &mut Vec<T>) -> Result<(), CliError> {
+        for column in self.columns.iter().chain(&self.sort_by) {
+            check_column(spec, column)?;
+        }
+
+        if let Some(column) = &self.sort_by {
+            let mut keyed: Vec<_> = items.drain(..).map(|i| (json!(i), i)).collect();
+            keyed.sort_by(|a, b| compare(&a.0[column], &b.0[column]));
+            items.extend(keyed.into_iter().map(|(_, i)| i));
+        }
+        if self.reverse {
+            items.reverse();
+        }
+        Ok(())
+    }
+
+    /// Prints items as table fitting terminal width
+    pub fn print<T: Serialize>(
+        &self,
+        spec: &TableSpec,
+        items: &[T],
+        cell: impl Fn(&str, &Value) -> String,
+    ) {
+        self.stream(spec, cell).push(items);
+    }
+
+    /// Table printed in parts, e.g. page by page
+    pub fn stream<'a, F: Fn(&str, &Value) -> String>(
+        &'a self,
+        spec: &'a TableSpec<'a>,
+        cell: F,
+    ) -> TableStream<'a, F> {
+        TableStream {
+            args: self,
+            spec,
+            cell,
+            widths: Vec::new(),
+        }
+    }
+
+    fn selected<'a>(&'a self, spec: &TableSpec<'a>) -> Vec<&'a str> {
+        if self.columns.is_empty() {
+            spec.columns.to_vec()
+        } else {
+            self.columns.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Renders rows. Header is rendered only when `widths` is empty.
+    /// Column widths only grow from part to part.
+    fn render(
+        &self,
+        spec: &TableSpec,
+        rows: &[Value],
+        max_width: Option<usize>,
+        cell: impl Fn(&str, &Value) -> String,
+        widths: &mut Vec<usize>,
+    ) -> String {
+        let columns = self.selected(spec);
+
+        let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|c| cell(c, &row[*c]).replace(['\n', '\r', '\t'], " "))
+                    .collect()
+            })
+            .collect();
+
+        let with_header = widths.is_empty();
+        if with_header {
+            *widths = header.iter().map(|h| h.width()).collect();
+        }
+        for row in &cells {
+            for (w, c) in widths.iter_mut().zip(row) {
+                *w = (*w).max(c.width());
+            }
+        }
+
+        // Shrink truncated column when table is wider than terminal
+        let mut shown = widths.clone();
+        if let (Some(max_width), Some(i)) = (
+            max_width,
+            spec.truncated
+                .and_then(|t| columns.iter().position(|c| *c == t)),
+        ) {
+            let total: usize = shown.iter().sum::<usize>() + SEPARATOR.len() * (shown.len() - 1);
+            if total > max_width {
+                let others = total - shown[i];
+                shown[i] = max_width
+                    .saturating_sub(others)
+                    .max(MIN_TRUNCATED_WIDTH)
+                    .min(shown[i]);
+            }
+        }
+
+        let header = with_header.then_some(&header);
+        let mut out = String::new();
+        for row in header.into_iter().chain(&cells) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&shown)
+                .map(|(c, w)| pad(&truncate(c, *w), *w))
+                .collect();
+            out.push_str(line.join(SEPARATOR).trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Table printed in parts with one header
+pub struct TableStream<'a, F> {
+    args: &'a TableArgs,
+    spec: &'a TableSpec<'a>,
+    cell: F,
+    widths: Vec<usize>,
+}
+
+impl<F: Fn(&str, &Value) -> String> TableStream<'_, F> {
+    pub fn push<T: Serialize>(&mut self, items: &[T]) {
+        let rows: Vec<Value> = items.iter().map(|i| json!(i)).collect();
+        let width = if io::stdout().is_terminal() {
+            terminal_size::terminal_size().map(|(w, _)| w.0 as usize)
+        } else {
+            None
+        };
+        print!(
+            "{}",
+            self.args
+                .render(self.spec, &rows, width, &self.cell, &mut self.widths)
+        );
+    }
+}
+
+fn check_column(spec: &TableSpec, column: &str) -> Result<(), CliError> {
+    if spec.columns.contains(&column) {
+        return Ok(());
+    }
+    Err(CliError::Usage(format!(
+        "Unknown column '{}', available: {}",
+        column,
+        spec.columns.join(", ")
+    )))
+}
+
+/// Default text of cell: strings without quotes, `-` for missing values
+pub fn default_cell(_column: &str, value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Numbers are compared by value, missing values go first
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .unwrap_or_default()
+            .total_cmp(&b.as_f64().unwrap_or_default()),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in s.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+fn pad(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(s.width())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SPEC: TableSpec = TableSpec {
+        columns: &["id", "lat", "descrip"],
+        truncated: Some("descrip"),
+    };
+
+    fn args(columns: &[&str], sort_by: Option<&str>, reverse: bool) -> TableArgs {
+        TableArgs {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            sort_by: sort_by.map(str::to_string),
+            reverse,
+            layout: Layout::Table,
+        }
+    }
+
+    fn rows() -> Vec<Value> {
+        vec![
+            json!({"id": 10, "lat": 55.5, "descrip": "Old oak near the river bank"}),
+            json!({"id": 2, "lat": 9.25, "descrip": "Bridge"}),
+        ]
+    }
+
+    #[test]
+    fn renders_aligned_columns() {
+        let table =
+            args(&[], None, false).render(&SPEC, &rows(), None, default_cell, &mut Vec::new());
+        assert_eq!(
+            table,
+            "ID  LAT   DESCRIP\n\
+             10  55.5  Old oak near the river bank\n\
+             2   9.25  Bridge\n"
+        );
+    }
+
+    #[test]
+    fn truncates_to_terminal_width() {
+        let table = args(&["id", "descrip"], None, false).render(
+            &SPEC,
+            &rows(),
+            Some(16),
+            default_cell,
+            &mut Vec::new(),
+        );
+        assert_eq!(table, "ID  DESCRIP\n10  Old oak nea…\n2   Bridge\n");
+    }
+
+    #[test]
+    fn sorts_numbers_by_value() {
+        let mut rows = rows();
+        args(&[], Some("id"), false).sort(&SPEC, &mut rows).unwrap();
+        assert_eq!(rows[0]["id"], 2);
+
+        args(&[], Some("lat"), true).sort(&SPEC, &mut rows).unwrap();
+        assert_eq!(rows[0]["id"], 10);
+    }
+
+    #[test]
+    fn rejects_unknown_column() {
+        let mut rows = rows();
+        assert!(args(&["hint"], None, false).sort(&SPEC, &mut rows).is_err());
+        assert!(args(&[], Some("owner"), false)
+            .sort(&SPEC, &mut rows)
+            .is_err());
+    }
+}
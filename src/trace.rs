@@ -0,0 +1,265 @@
+//! Tracing of HTTP requests for diagnosing server issues
+//!
+//! Levels of `-v`:
+//! * 1 - request line, response status with total time and response body
+//! * 2 - also headers, request body and time to response headers
+//!
+//! `--trace-file` gets all details of every event as JSON lines. Api key and
+//! passwords are redacted everywhere.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use msd_client::{PreparedRequest, TraceEvent, TracedResponse};
+use serde_json::{Map, Value};
+
+use crate::{error::CliError, secrets};
+
+pub struct Tracer {
+    level: u8,
+    file: Option<File>,
+}
+
+impl Tracer {
+    pub fn new(level: u8, file: Option<&Path>) -> Result<Self, CliError> {
+        let file = match file {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| {
+                        CliError::Local(format!("Failed to open {}: {}", path.display(), e))
+                    })?,
+            ),
+            None => None,
+        };
+        Ok(Self { level, file })
+    }
+
+    pub fn event(&self, event: &TraceEvent) {
+        if self.level > 0 {
+            eprint!("{}", self.render(event));
+        }
+        if let Some(mut file) = self.file.as_ref() {
+            // Failed trace must not break the request
+            if let Err(e) = writeln!(file, "{}", record(event)) {
+                eprintln!("Failed to write trace: {}", e);
+            }
+        }
+    }
+
+    /// Text of event on stderr in style of `curl -v`
+    fn render(&self, event: &TraceEvent) -> String {
+        let verbose = self.level > 1;
+        let mut out = String::new();
+
+        match event {
+            TraceEvent::Request { attempt, request } => {
+                let retry = match attempt {
+                    0 => String::new(),
+                    n => format!(" (retry {})", n),
+                };
+                out.push_str(&format!("> {} {}{}\n", request.method, request.url, retry));
+                if verbose {
+                    for (k, v) in &request.headers {
+                        out.push_str(&format!("> {}: {}\n", k, secrets::redact_header(k, v)));
+                    }
+                    if let Some(body) = &request.body {
+                        out.push_str(&format!(">\n> {}\n", secrets::redact(body)));
+                    }
+                }
+            }
+            TraceEvent::Response { response, .. } => {
+                let time = if verbose {
+                    format!(
+                        "headers {}, total {}",
+                        millis(response.headers_time),
+                        millis(response.total_time)
+                    )
+                } else {
+                    millis(response.total_time)
+                };
+                out.push_str(&format!("< {} ({})\n", response.status, time));
+                if verbose {
+                    for (k, v) in &response.headers {
+                        out.push_str(&format!("< {}: {}\n", k, v));
+                    }
+                    out.push_str("<\n");
+                }
+                if !response.body.is_empty() {
+                    let body = match body_json(&response.body) {
+                        Some(json) => serde_json::to_string_pretty(&json).unwrap_or_default(),
+                        None => response.body.clone(),
+                    };
+                    out.push_str(&body);
+                    out.push('\n');
+                }
+            }
+            TraceEvent::Failed { error, elapsed, .. } => {
+                out.push_str(&format!("* Failed after {}: {}\n", millis(*elapsed), error));
+            }
+            TraceEvent::Retry { delay, .. } => {
+                out.push_str(&format!("* Retrying in {}\n", millis(*delay)));
+            }
+        }
+        out
+    }
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
+
+/// Redacted JSON of body, `None` when body is not JSON
+fn body_json(body: &str) -> Option<Value> {
+    serde_json::from_str(body).ok().map(|v| secrets::redact(&v))
+}
+
+fn headers(headers: &[(String, String)]) -> Map<String, Value> {
+    headers
+        .iter()
+        .map(|(k, v)| (k.clone(), json!(secrets::redact_header(k, v))))
+        .collect()
+}
+
+/// Line of trace file
+fn record(event: &TraceEvent) -> Value {
+    let time = humantime::format_rfc3339_millis(SystemTime::now()).to_string();
+
+    match event {
+        TraceEvent::Request {
+            attempt,
+            request:
+                PreparedRequest {
+                    method,
+                    url,
+                    headers: request_headers,
+                    body,
+                },
+        } => json!({
+            "time": time,
+            "event": "request",
+            "attempt": attempt,
+            "method": method,
+            "url": url,
+            "headers": headers(request_headers),
+            "body": body.as_ref().map(secrets::redact),
+        }),
+        TraceEvent::Response {
+            attempt,
+            response:
+                TracedResponse {
+                    status,
+                    headers: response_headers,
+                    body,
+                    headers_time,
+                    total_time,
+                },
+        } => json!({
+            "time": time,
+            "event": "response",
+            "attempt": attempt,
+            "status": status,
+            "headers": headers(response_headers),
+            // Body which is not JSON is kept as text
+            "body": body_json(body).unwrap_or_else(|| json!(body)),
+            "headers_ms": headers_time.as_secs_f64() * 1000.0,
+            "total_ms": total_time.as_secs_f64() * 1000.0,
+        }),
+        TraceEvent::Failed {
+            attempt,
+            error,
+            elapsed,
+        } => json!({
+            "time": time,
+            "event": "failed",
+            "attempt": attempt,
+            "error": error,
+            "elapsed_ms": elapsed.as_secs_f64() * 1000.0,
+        }),
+        TraceEvent::Retry { attempt, delay } => json!({
+            "time": time,
+            "event": "retry",
+            "attempt": attempt,
+            "delay_ms": delay.as_secs_f64() * 1000.0,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn request() -> TraceEvent {
+        TraceEvent::Request {
+            attempt: 0,
+            request: PreparedRequest {
+                method: "POST".to_string(),
+                url: "http://127.0.0.1:8000/api/v1/user".to_string(),
+                headers: vec![
+                    ("X-API-Key".to_string(), "secret-key".to_string()),
+                    ("accept".to_string(), "*/*".to_string()),
+                ],
+                body: Some(json!({ "email": "a@b", "password": "secret-password" })),
+            },
+        }
+    }
+
+    fn response() -> TraceEvent {
+        TraceEvent::Response {
+            attempt: 0,
+            response: TracedResponse {
+                status: 200,
+                headers: vec![("x-api-key".to_string(), "secret-echo".to_string())],
+                body: r#"{"id":1,"api_key":"secret-new-key"}"#.to_string(),
+                headers_time: Duration::from_millis(1),
+                total_time: Duration::from_millis(2),
+            },
+        }
+    }
+
+    #[test]
+    fn redacts_secrets_on_stderr() {
+        let tracer = Tracer::new(2, None).unwrap();
+
+        let text = tracer.render(&request());
+        assert!(text.contains("> X-API-Key: <redacted>"));
+        assert!(text.contains("> accept: */*"));
+        assert!(text.contains(r#""password":"<redacted>""#));
+        assert!(!text.contains("secret"));
+
+        let text = tracer.render(&response());
+        assert!(text.contains(r#""api_key": "<redacted>""#));
+        assert!(!text.contains("secret-new-key"));
+    }
+
+    #[test]
+    fn redacts_secrets_in_file() {
+        let path = std::env::temp_dir().join(format!("msd-trace-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let tracer = Tracer::new(0, Some(&path)).unwrap();
+        tracer.event(&request());
+        tracer.event(&response());
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(!content.contains("secret"));
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines[0]["headers"]["X-API-Key"], "<redacted>");
+        assert_eq!(lines[0]["headers"]["accept"], "*/*");
+        assert_eq!(lines[0]["body"]["password"], "<redacted>");
+        assert_eq!(lines[0]["body"]["email"], "a@b");
+        assert_eq!(lines[1]["headers"]["x-api-key"], "<redacted>");
+        assert_eq!(lines[1]["body"]["api_key"], "<redacted>");
+    }
+}
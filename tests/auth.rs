@@ -0,0 +1,211 @@
+mod common;
+
+use std::{ffi::OsStr, fs, process::Command};
+
+use common::{assert_success, run_with_stdin, stderr, stdout, Mock, BIN};
+
+/// Runs binary without `--credentials`, so keys are kept in system keyring
+/// or default file. `secret-tool` is searched in `path`.
+fn run_with_keyring(mock: &Mock, path: &OsStr, args: &[&str], stdin: &str) -> std::process::Output {
+    let mut cmd = Command::new(BIN);
+    cmd.args(["--base-url", mock.url(), "--retries", "0"])
+        .arg("--config")
+        .arg(mock.path("config.toml"))
+        .arg("--journal")
+        .arg(mock.path("journal.jsonl"))
+        .args(args)
+        .env("PATH", path)
+        .env("XDG_DATA_HOME", mock.path("data"))
+        .env_remove("MSD_API_KEY");
+    run_with_stdin(cmd, stdin)
+}
+
+#[test]
+fn login_supplies_api_key() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("alice");
+    let id = id.to_string();
+
+    let output = mock.run_with_stdin(
+        &["login", "--id", &id, "--key-stdin"],
+        &format!("{}\n", key),
+    );
+    assert_success(&output);
+    assert!(stdout(&output).contains("as user 1"));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(mock.path("credentials.json"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let whoami = mock.json(&["whoami"]);
+    assert_eq!(whoami["login"], "alice");
+    assert_eq!(whoami["store"], "file");
+    assert_eq!(whoami["active"], true);
+
+    let cache = mock.json(&[
+        "cache",
+        "create",
+        "--lat=1",
+        "--long=2",
+        "--descrip",
+        "Oak",
+        "--hint",
+        "Roots",
+    ]);
+    assert_eq!(cache["owner"], 1);
+
+    let logout = mock.json(&["logout"]);
+    assert_eq!(logout["logged_out"], true);
+    assert_eq!(mock.run(&["whoami"]).status.code(), Some(2));
+    assert_eq!(mock.json(&["logout"])["logged_out"], false);
+}
+
+#[test]
+fn login_rejects_key_of_other_user() {
+    let mock = Mock::start();
+    mock.create_user("alice");
+    let (_, key) = mock.create_user("bob");
+
+    let output = mock.run_with_stdin(
+        &["login", "--id", "1", "--key-stdin"],
+        &format!("{}\n", key),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("not accepted as key of user 1"));
+    assert!(!mock.path("credentials.json").exists());
+
+    // Other errors are reported as they are
+    let output = mock.run_with_stdin(
+        &["login", "--id", "99", "--key-stdin"],
+        &format!("{}\n", key),
+    );
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr(&output).contains("User not found"));
+    assert!(!mock.path("credentials.json").exists());
+}
+
+#[test]
+fn logout_removes_corrupt_credentials() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    std::fs::write(mock.path("credentials.json"), "{ not json").unwrap();
+
+    // Commands not using stored key are not affected
+    assert_success(&mock.run(&["--api", &key, "user", "view", "--id", "1"]));
+    assert_success(&mock.run(&["config", "list"]));
+
+    let output = mock.run(&["user", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("logout removes it"));
+
+    let output = mock.run(&["logout"]);
+    assert_success(&output);
+    assert!(!mock.path("credentials.json").exists());
+    assert_eq!(mock.run(&["whoami"]).status.code(), Some(2));
+}
+
+#[test]
+fn falls_back_to_file_without_keyring() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    // No secret-tool is found in empty directory
+    let bin = mock.path("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let path = bin.as_os_str();
+
+    let output = run_with_keyring(
+        &mock,
+        path,
+        &["login", "--id", "1", "--key-stdin", "--output", "json"],
+        &format!("{}\n", key),
+    );
+    assert_success(&output);
+    let login: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(login["store"], "file");
+
+    let file = mock.path("data").join("msd-cli").join("credentials.json");
+    assert!(fs::read_to_string(&file).unwrap().contains(&key));
+
+    let output = run_with_keyring(&mock, path, &["whoami"], "");
+    assert_success(&output);
+    assert!(stdout(&output).contains(&file.display().to_string()));
+}
+
+#[cfg(unix)]
+#[test]
+fn stores_key_in_keyring() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    // Fake secret-tool keeping one secret next to itself
+    let bin = mock.path("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let tool = bin.join("secret-tool");
+    fs::write(
+        &tool,
+        "#!/bin/sh\necho \"$1\" >> \"$(dirname \"$0\")/calls\"\nsecret=\"$(dirname \"$0\")/secret\"\ncase \"$1\" in\n  store) cat > \"$secret\" ;;\n  lookup) cat \"$secret\" ;;\n  clear) rm -f \"$secret\" ;;\nesac\n",
+    )
+    .unwrap();
+    fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(std::iter::once(bin.clone()).chain(std::env::split_paths(
+        &std::env::var_os("PATH").unwrap_or_default(),
+    )))
+    .unwrap();
+    let path = path.as_os_str();
+
+    let output = run_with_keyring(
+        &mock,
+        path,
+        &["login", "--id", "1", "--key-stdin"],
+        &format!("{}\n", key),
+    );
+    assert_success(&output);
+    assert!(stdout(&output).contains("system keyring"));
+    assert!(fs::read_to_string(bin.join("secret"))
+        .unwrap()
+        .contains(&key));
+    assert!(!mock
+        .path("data")
+        .join("msd-cli")
+        .join("credentials.json")
+        .exists());
+
+    let output = run_with_keyring(&mock, path, &["user", "keys", "view", "--id", "1"], "");
+    assert_success(&output);
+    assert_eq!(
+        fs::read_to_string(bin.join("calls")).unwrap(),
+        "store\nlookup\n"
+    );
+
+    // Anonymous request does not read keyring
+    let output = run_with_keyring(
+        &mock,
+        path,
+        &[
+            "user",
+            "create",
+            "--name",
+            "bob",
+            "--email",
+            "bob@example.com",
+            "--password-stdin",
+        ],
+        "password1\n",
+    );
+    assert_success(&output);
+    assert_eq!(
+        fs::read_to_string(bin.join("calls")).unwrap(),
+        "store\nlookup\n"
+    );
+
+    let output = run_with_keyring(&mock, path, &["logout"], "");
+    assert_success(&output);
+    assert!(!bin.join("secret").exists());
+}
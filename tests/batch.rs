@@ -0,0 +1,88 @@
+mod common;
+
+use common::{assert_success, stdout, Mock};
+
+static SCRIPT: &str = "# Provision user with a cache
+user create --name alice --email alice@example.com --password password1
+set api $LAST_KEY
+user keys generate --id $LAST_ID
+cache create --lat 1 --long 2 --descrip Oak --hint Roots
+cache change --id $LAST_ID --hint \"Under roots\"
+";
+
+#[test]
+fn runs_script_with_variables() {
+    let mock = Mock::start();
+    let script = mock.path("setup.msd");
+    std::fs::write(&script, SCRIPT).unwrap();
+
+    let output = mock.run(&["batch", script.to_str().unwrap()]);
+    assert_success(&output);
+    assert!(stdout(&output).ends_with("Batch finished: 5 commands succeeded, 0 failed\n"));
+
+    let (_, key) = mock.create_user("bob");
+    let cache = mock.json(&["--api", &key, "cache", "view", "--id", "1"]);
+    assert_eq!(cache["owner"], 1);
+    assert_eq!(cache["hint"], "Under roots");
+}
+
+#[test]
+fn stops_or_continues_on_error() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    let script = "cache view --id 5\ncache create --lat 1 --long 2 --descrip Oak --hint Roots\n";
+
+    let output = mock.run_with_stdin(&["--api", &key, "batch", "-"], script);
+    assert_eq!(output.status.code(), Some(8));
+    let caches = mock.json(&["--api", &key, "cache", "find"]);
+    assert!(caches.as_array().unwrap().is_empty());
+
+    let output = mock.run_with_stdin(
+        &[
+            "--api",
+            &key,
+            "--output",
+            "json",
+            "batch",
+            "--continue-on-error",
+            "-",
+        ],
+        script,
+    );
+    assert_eq!(output.status.code(), Some(8));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["succeeded"], 1);
+    assert_eq!(summary["commands"][0]["line"], 1);
+    assert!(summary["commands"][0]["error"].is_string());
+    assert_eq!(summary["commands"][1]["result"]["id"], 1);
+}
+
+#[test]
+fn dry_run_prints_request_of_every_line() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    let script = "cache create --lat 1 --long 2 --descrip Oak --hint Roots
+cache create --lat 3 --long 4 --descrip Elm --hint Bark
+cache change --id $LAST_ID --hint Hollow
+";
+
+    let output = mock.run_with_stdin(
+        &["--api", &key, "--dry-run", "--output", "json", "batch", "-"],
+        script,
+    );
+    assert_eq!(output.status.code(), Some(8));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["succeeded"], 2);
+    for i in 0..2 {
+        assert_eq!(summary["commands"][i]["result"]["dry_run"], true);
+        assert_eq!(summary["commands"][i]["result"]["method"], "POST");
+    }
+    assert_eq!(summary["commands"][1]["result"]["body"]["descrip"], "Elm");
+    // Nothing is created, so there is no ID
+    assert!(summary["commands"][2]["error"]
+        .as_str()
+        .unwrap()
+        .contains("$LAST_ID"));
+    let caches = mock.json(&["--api", &key, "cache", "find"]);
+    assert!(caches.as_array().unwrap().is_empty());
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use common::{assert_success, stderr, stdout, Mock};
+
+/// Mock with user owning caches in Moscow and one in London. Returns api key.
+fn mock_with_caches() -> (Mock, String) {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    mock.create_cache(&key, 55.7539, 37.6208, "Red square");
+    mock.create_cache(&key, 55.7601, 37.6186, "Bolshoi");
+    mock.create_cache(&key, 51.5007, -0.1246, "Big Ben");
+    (mock, key)
+}
+
+fn ids(caches: &serde_json::Value) -> Vec<i64> {
+    caches
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[test]
+fn creates_and_views_cache() {
+    let mock = Mock::start();
+    let (user, key) = mock.create_user("alice");
+    let id = mock.create_cache(&key, 10.5, 20.25, "Old oak");
+
+    let cache = mock.json(&["--api", &key, "cache", "view", "--id", &id.to_string()]);
+    assert_eq!(cache["owner"], user);
+    assert_eq!(cache["lat"], 10.5);
+    assert_eq!(cache["descrip"], "Old oak");
+}
+
+#[test]
+fn changes_and_deletes_cache() {
+    let (mock, key) = mock_with_caches();
+
+    mock.json(&[
+        "--api",
+        &key,
+        "cache",
+        "change",
+        "--id",
+        "1",
+        "--hint",
+        "Under bench",
+    ]);
+    let cache = mock.json(&["--api", &key, "cache", "view", "--id", "1"]);
+    assert_eq!(cache["hint"], "Under bench");
+    assert_eq!(cache["descrip"], "Red square");
+
+    mock.json(&["--api", &key, "cache", "delete", "--id", "1"]);
+    let output = mock.run(&["--api", &key, "cache", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn only_owner_changes_cache() {
+    let (mock, _) = mock_with_caches();
+    let (_, other) = mock.create_user("bob");
+
+    let output = mock.run(&["--api", &other, "cache", "delete", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr(&output).contains("Forbidden"));
+}
+
+#[test]
+fn finds_by_bounds_and_owner() {
+    let (mock, key) = mock_with_caches();
+    let (_, other) = mock.create_user("bob");
+    mock.create_cache(&other, 55.75, 37.62, "Kremlin");
+
+    let found = mock.json(&[
+        "--api",
+        &key,
+        "cache",
+        "find",
+        "--min-lat",
+        "55",
+        "--max-lat",
+        "56",
+        "--min-long",
+        "37",
+        "--max-long",
+        "38",
+    ]);
+    assert_eq!(ids(&found), [1, 2, 4]);
+
+    let found = mock.json(&["--api", &key, "cache", "find", "--user", "2"]);
+    assert_eq!(ids(&found), [4]);
+}
+
+#[test]
+fn finds_near_point() {
+    let (mock, key) = mock_with_caches();
+
+    let found = mock.json(&[
+        "--api",
+        &key,
+        "cache",
+        "find",
+        "--near",
+        "55.7558,37.6173",
+        "--radius",
+        "2km",
+    ]);
+    assert_eq!(ids(&found), [1, 2]);
+    assert!(found[0]["distance"].as_f64().unwrap() < found[1]["distance"].as_f64().unwrap());
+}
+
+#[test]
+fn pages_results() {
+    let (mock, key) = mock_with_caches();
+
+    let page = mock.json(&[
+        "--api", &key, "cache", "find", "--limit", "1", "--offset", "1",
+    ]);
+    assert_eq!(ids(&page), [2]);
+
+    let all = mock.json(&["--api", &key, "cache", "find", "--all", "--page-size", "2"]);
+    assert_eq!(ids(&all), [1, 2, 3]);
+}
+
+#[test]
+fn sorts_and_prints_table() {
+    let (mock, key) = mock_with_caches();
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "cache",
+        "find",
+        "--columns",
+        "id,descrip",
+        "--sort-by",
+        "descrip",
+    ]);
+    assert_success(&output);
+    assert_eq!(
+        stdout(&output),
+        "ID  DESCRIP\n3   Big Ben\n2   Bolshoi\n1   Red square\n"
+    );
+}
+
+#[test]
+fn exports_to_file() {
+    let (mock, key) = mock_with_caches();
+    let file = mock.path("caches.gpx");
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "cache",
+        "find",
+        "--export",
+        "gpx",
+        "--export-file",
+        file.to_str().unwrap(),
+    ]);
+    assert_success(&output);
+    let gpx = std::fs::read_to_string(file).unwrap();
+    assert_eq!(gpx.matches("<wpt").count(), 3);
+}
+
+#[test]
+fn dry_run_sends_nothing() {
+    let (mock, key) = mock_with_caches();
+
+    let request = mock.json(&["--api", &key, "--dry-run", "cache", "delete", "--id", "1"]);
+    assert_eq!(request["method"], "DELETE");
+    assert_eq!(request["headers"]["x-api-key"], "<redacted>");
+
+    let cache = mock.json(&["--api", &key, "cache", "view", "--id", "1"]);
+    assert_eq!(cache["id"], 1);
+}
+
+#[test]
+fn prints_curl_commands() {
+    let (mock, key) = mock_with_caches();
+
+    let output = mock.run(&["--api", &key, "--print-curl", "cache", "view", "--id", "2"]);
+    assert_success(&output);
+    assert!(stderr(&output).contains(&format!("curl {}/cache/2", mock.url())));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::Mock;
+
+#[test]
+fn replays_recorded_session() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("alice");
+    let id = id.to_string();
+    mock.create_cache(&key, 1.0, 2.0, "Oak");
+    let dir = mock.path("cassette");
+    let dir = dir.to_str().unwrap();
+
+    let find = ["--api", &key, "--record", dir, "cache", "find"];
+    let recorded_caches = mock.json(&find);
+    let view = [
+        "--api", &key, "--record", dir, "user", "keys", "view", "--id", &id,
+    ];
+    mock.json(&view);
+
+    let files = std::fs::read_dir(dir).unwrap().count();
+    assert_eq!(files, 2);
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let text = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        assert!(!text.contains(&key));
+    }
+
+    // Server state changes, replayed responses do not
+    mock.json(&["--api", &key, "cache", "delete", "--id", "1"]);
+
+    let caches = mock.json(&["--api", &key, "--replay", dir, "cache", "find"]);
+    assert_eq!(caches, recorded_caches);
+    let keys = mock.json(&[
+        "--api", &key, "--replay", dir, "user", "keys", "view", "--id", &id,
+    ]);
+    assert_eq!(keys[0]["nmb"], 1);
+    assert_eq!(keys[0]["api_key"], "<redacted>");
+
+    let output = mock.run(&["--api", &key, "--replay", dir, "cache", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(9));
+}
@@ -0,0 +1,200 @@
+//! Harness running the binary against its own `mock-server`
+//!
+//! Every [`Mock`] starts a server on a free port with empty state, and runs
+//! commands with a config file, journal and credentials file of its own
+//! temporary directory.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    process::{Child, Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde_json::Value;
+
+pub static BIN: &str = env!("CARGO_BIN_EXE_msd-cli");
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Mock {
+    server: Child,
+    url: String,
+    dir: PathBuf,
+}
+
+impl Mock {
+    pub fn start() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "msd-cli-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut server = Command::new(BIN)
+            .args(["mock-server", "--port", "0"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        // First line announces base URL
+        let mut line = String::new();
+        BufReader::new(server.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let url = line.trim().rsplit(' ').next().unwrap().to_string();
+
+        Self { server, url, dir }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// File in temporary directory of test
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Command of binary using mock server without retries
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut cmd = self.base_command();
+        cmd.arg("--base-url").arg(&self.url).args(args);
+        cmd
+    }
+
+    /// Command of binary without base URL of mock server, so the one of
+    /// profile is used
+    pub fn local_command(&self, args: &[&str]) -> Command {
+        let mut cmd = self.base_command();
+        cmd.args(args);
+        cmd
+    }
+
+    /// Binary with config, journal and credentials files of test
+    fn base_command(&self) -> Command {
+        let mut cmd = Command::new(BIN);
+        cmd.env_remove("MSD_API_KEY")
+            .arg("--config")
+            .arg(self.path("config.toml"))
+            .arg("--journal")
+            .arg(self.path("journal.jsonl"))
+            .arg("--credentials")
+            .arg(self.path("credentials.json"))
+            .args(["--retries", "0"]);
+        cmd
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.run_with_stdin(args, "")
+    }
+
+    pub fn run_with_stdin(&self, args: &[&str], stdin: &str) -> Output {
+        run_with_stdin(self.command(args), stdin)
+    }
+
+    /// Runs command without base URL of mock server, see `local_command`
+    pub fn run_local(&self, args: &[&str]) -> Output {
+        run_with_stdin(self.local_command(args), "")
+    }
+
+    /// Runs command with `--output json`, which must succeed
+    pub fn json(&self, args: &[&str]) -> Value {
+        let mut args = args.to_vec();
+        args.extend(["--output", "json"]);
+        let output = self.run(&args);
+        assert_success(&output);
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    /// Registers user. Returns ID and api key.
+    pub fn create_user(&self, login: &str) -> (i32, String) {
+        let email = format!("{}@example.com", login);
+        let output = self.run_with_stdin(
+            &[
+                "user",
+                "create",
+                "--name",
+                login,
+                "--email",
+                &email,
+                "--password-stdin",
+                "--output",
+                "json",
+            ],
+            "password1\n",
+        );
+        assert_success(&output);
+
+        let created: Value = serde_json::from_slice(&output.stdout).unwrap();
+        (
+            created["id"].as_i64().unwrap() as i32,
+            created["api_key"].as_str().unwrap().to_string(),
+        )
+    }
+
+    /// Creates cache of user with `key`. Returns its ID.
+    pub fn create_cache(&self, key: &str, lat: f64, long: f64, descrip: &str) -> i32 {
+        let created = self.json(&[
+            "--api",
+            key,
+            "cache",
+            "create",
+            &format!("--lat={}", lat),
+            &format!("--long={}", long),
+            "--descrip",
+            descrip,
+            "--hint",
+            "hint",
+        ]);
+        created["id"].as_i64().unwrap() as i32
+    }
+}
+
+impl Drop for Mock {
+    fn drop(&mut self) {
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Runs `command` writing `stdin` to it
+pub fn run_with_stdin(mut command: Command, stdin: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+pub fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "status {:?}\nstdout: {}\nstderr: {}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
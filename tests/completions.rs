@@ -0,0 +1,102 @@
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{assert_success, stdout, Mock, BIN};
+
+/// Name the binary is installed with
+fn bin_name() -> &'static str {
+    Path::new(BIN).file_stem().unwrap().to_str().unwrap()
+}
+
+#[test]
+fn prints_completion_scripts() {
+    let mock = Mock::start();
+
+    let output = mock.run(&["completions", "bash"]);
+    assert_success(&output);
+    let script = stdout(&output);
+    assert!(script.contains(&format!("{} complete-ids ${{kind}}", bin_name())));
+
+    let output = mock.run(&["completions", "zsh"]);
+    assert_success(&output);
+    assert!(stdout(&output).contains(&format!("#compdef {}", bin_name())));
+}
+
+#[test]
+fn bash_script_completes_installed_binary() {
+    let mock = Mock::start();
+    let (_, alice) = mock.create_user("alice");
+    mock.create_cache(&alice, 1.0, 1.0, "One");
+    mock.create_cache(&alice, 2.0, 2.0, "Two");
+
+    let script = mock.path("msd.bash");
+    fs::write(&script, stdout(&mock.run(&["completions", "bash"]))).unwrap();
+    // Binary run by script finds mock server in its default config
+    let config = mock.path("xdg-config").join("msd-cli");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(
+        config.join("config.toml"),
+        format!(
+            "default_profile = \"mock\"\n\n[profiles.mock]\nbase_url = \"{}\"\napi_key = \"{}\"\n",
+            mock.url(),
+            alice
+        ),
+    )
+    .unwrap();
+
+    let path = format!(
+        "{}:{}",
+        Path::new(BIN).parent().unwrap().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(
+            r#"source "$1"
+            func=$(complete -p "$2" | sed -n 's/.* -F \([^ ]*\) .*/\1/p')
+            [ -n "$func" ] || exit 1
+            COMP_WORDS=("$2" cache view --id "")
+            COMP_CWORD=4
+            "$func" "$2" "" --id
+            echo "${COMPREPLY[*]}""#,
+        )
+        .args(["bash", script.to_str().unwrap(), bin_name()])
+        .env("PATH", path)
+        .env("XDG_CONFIG_HOME", mock.path("xdg-config"))
+        .env("XDG_DATA_HOME", mock.path("xdg-data"))
+        .env_remove("MSD_API_KEY")
+        .output()
+        .unwrap();
+    assert_success(&output);
+    assert_eq!(stdout(&output), "1 2\n");
+}
+
+#[test]
+fn completes_ids_from_server() {
+    let mock = Mock::start();
+    let (_, alice) = mock.create_user("alice");
+    let (_, bob) = mock.create_user("bob");
+    mock.create_cache(&alice, 1.0, 1.0, "One");
+    mock.create_cache(&bob, 2.0, 2.0, "Two");
+
+    let output = mock.run(&["--api", &alice, "complete-ids", "caches"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output), "1\n2\n");
+
+    let output = mock.run(&["--api", &alice, "complete-ids", "users"]);
+    assert_eq!(stdout(&output), "1\n2\n");
+}
+
+#[test]
+fn writes_man_pages() {
+    let mock = Mock::start();
+    let dir = mock.path("man");
+
+    let output = mock.run(&["man", dir.to_str().unwrap()]);
+    assert_success(&output);
+
+    let page = fs::read_to_string(dir.join(format!("{}-cache-find.1", bin_name()))).unwrap();
+    assert!(page.contains(&format!(".TH {}-CACHE-FIND 1", bin_name().to_uppercase())));
+    assert!(dir.join(format!("{}.1", bin_name())).exists());
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{assert_success, stderr, Mock};
+
+#[test]
+fn manages_profiles() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+
+    let output = mock.run_with_stdin(
+        &[
+            "config",
+            "add",
+            "local",
+            "--profile-base-url",
+            mock.url(),
+            "--api-key-stdin",
+            "--default",
+        ],
+        &format!("{}\n", key),
+    );
+    assert_success(&output);
+    mock.json(&[
+        "config",
+        "add",
+        "other",
+        "--profile-base-url",
+        "http://127.0.0.1:1",
+    ]);
+
+    // Profiles may have api keys
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(mock.path("config.toml"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let profiles = mock.json(&["config", "list"]);
+    assert_eq!(
+        profiles,
+        serde_json::json!([
+            { "name": "local", "default": true },
+            { "name": "other", "default": false },
+        ])
+    );
+
+    let shown = mock.json(&["config", "show"]);
+    assert_eq!(shown["name"], "local");
+    assert_eq!(
+        shown["profile"]["api_key"],
+        format!("****{}", &key[key.len() - 4..])
+    );
+
+    // Short key is not shown at all
+    let path = mock.path("short-key");
+    std::fs::write(&path, "abcd\n").unwrap();
+    mock.json(&[
+        "config",
+        "add",
+        "short",
+        "--api-key-file",
+        path.to_str().unwrap(),
+    ]);
+    let short = mock.json(&["config", "show", "--profile", "short"]);
+    assert_eq!(short["profile"]["api_key"], "****");
+    mock.json(&["config", "remove", "short"]);
+
+    // Global --base-url of harness is not stored in profile
+    let other = mock.json(&["config", "show", "--profile", "other"]);
+    assert_eq!(other["profile"]["base_url"], "http://127.0.0.1:1");
+
+    mock.json(&["config", "remove", "other"]);
+    assert_eq!(mock.json(&["config", "list"]).as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn uses_api_key_of_profile() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("bob");
+    mock.json(&["config", "add", "local", "--api-key", &key, "--default"]);
+
+    // Base URL is given by harness, api key comes from profile
+    let output = mock.run(&["user", "view", "--id", "1"]);
+    assert_success(&output);
+
+    let output = mock.run(&["--profile", "missing", "user", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn config_commands_work_with_broken_profile() {
+    let mock = Mock::start();
+    std::fs::write(
+        mock.path("config.toml"),
+        "default_profile = \"bad\"\n\n[profiles.bad]\nbase_url = \"foo\"\n",
+    )
+    .unwrap();
+
+    let output = mock.run_local(&["user", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Base URL must start with"));
+
+    assert_success(&mock.run_local(&["config", "list"]));
+    assert_success(&mock.run_local(&["completions", "bash"]));
+    assert_success(&mock.run_local(&["config", "remove", "bad"]));
+}
+
+#[test]
+fn config_commands_work_with_missing_default_profile() {
+    let mock = Mock::start();
+    std::fs::write(mock.path("config.toml"), "default_profile = \"gone\"\n").unwrap();
+
+    let output = mock.run(&["user", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Profile 'gone' not found"));
+
+    let output = mock.run_local(&["config", "add", "gone", "--profile-base-url", mock.url()]);
+    assert_success(&output);
+    assert_success(&mock.run(&["config", "show"]));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{assert_success, stdout, Mock};
+
+static CSV: &str = "lat,long,descrip,hint\n\
+                    55.75,37.62,Red square,Near wall\n\
+                    95,37.62,Too far north,None\n\
+                    51.5,-0.12,Big Ben,Clock\n";
+
+#[test]
+fn imports_valid_rows() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    let file = mock.path("caches.csv");
+    std::fs::write(&file, CSV).unwrap();
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "cache",
+        "import",
+        file.to_str().unwrap(),
+        "--output",
+        "json",
+    ]);
+    // Invalid row fails the import partially
+    assert_eq!(output.status.code(), Some(8));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["created"].as_array().unwrap().len(), 2);
+    assert_eq!(result["failed"][0]["row"], 3);
+
+    let caches = mock.json(&["--api", &key, "cache", "find"]);
+    assert_eq!(caches.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn dry_run_previews_rows() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("bob");
+    let file = mock.path("caches.csv");
+    std::fs::write(&file, "lat,long,descrip,hint\n1,2,One,Hint\n").unwrap();
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "--dry-run",
+        "cache",
+        "import",
+        file.to_str().unwrap(),
+    ]);
+    assert_success(&output);
+    assert!(stdout(&output).contains("1 caches to create"));
+
+    let caches = mock.json(&["--api", &key, "cache", "find"]);
+    assert!(caches.as_array().unwrap().is_empty());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{assert_success, stdout, Mock};
+
+#[test]
+fn records_history_of_changes() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    let id = mock.create_cache(&key, 1.0, 2.0, "Oak").to_string();
+    mock.json(&[
+        "--api", &key, "cache", "change", "--id", &id, "--hint", "Roots",
+    ]);
+
+    let history = mock.json(&["history"]);
+    let operations: Vec<_> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["operation"].as_str().unwrap())
+        .collect();
+    assert_eq!(operations, ["user_create", "cache_create", "cache_change"]);
+    assert_eq!(history[2]["before"]["hint"], "hint");
+    assert_eq!(history[0]["request"]["password"], "<redacted>");
+
+    let output = mock.run(&["history", "--limit", "1"]);
+    assert_success(&output);
+    assert!(stdout(&output).starts_with("#3 "));
+}
+
+#[test]
+fn undoes_change_and_delete() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("bob");
+    let id = mock.create_cache(&key, 1.0, 2.0, "Oak").to_string();
+
+    mock.json(&[
+        "--api",
+        &key,
+        "cache",
+        "change",
+        "--id",
+        &id,
+        "--descrip",
+        "Elm",
+    ]);
+    mock.json(&["--api", &key, "undo", "3"]);
+    let cache = mock.json(&["--api", &key, "cache", "view", "--id", &id]);
+    assert_eq!(cache["descrip"], "Oak");
+
+    // Entry 4 is the undo, entry 5 the delete
+    mock.json(&["--api", &key, "cache", "delete", "--id", &id]);
+    let undone = mock.json(&["--api", &key, "undo", "5"]);
+    assert_eq!(undone["undone"], 5);
+    let new_id = undone["id"].to_string();
+    assert_ne!(new_id, id);
+
+    let cache = mock.json(&["--api", &key, "cache", "view", "--id", &new_id]);
+    assert_eq!(cache["descrip"], "Oak");
+
+    let output = mock.run(&["--api", &key, "undo", "5"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn user_creation_can_not_be_undone() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("carol");
+
+    let output = mock.run(&["--api", &key, "undo", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+}
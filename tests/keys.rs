@@ -0,0 +1,73 @@
+mod common;
+
+use common::{assert_success, stdout, Mock};
+
+#[test]
+fn generates_views_and_revokes_keys() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("alice");
+    let id = id.to_string();
+
+    let generated = mock.json(&["--api", &key, "user", "keys", "generate", "--id", &id]);
+    let new_key = generated["key"].as_str().unwrap().to_string();
+
+    let keys = mock.json(&["--api", &key, "user", "keys", "view", "--id", &id]);
+    assert_eq!(keys.as_array().unwrap().len(), 2);
+    assert_eq!(keys[1]["nmb"], 2);
+    assert_eq!(keys[1]["api_key"], new_key.as_str());
+
+    let single = mock.json(&[
+        "--api", &key, "user", "keys", "view", "--id", &id, "--nmb", "2",
+    ]);
+    assert_eq!(single[0]["api_key"], new_key.as_str());
+
+    let revoked = mock.json(&[
+        "--api", &key, "user", "keys", "revoke", "--id", &id, "--nmb", "2",
+    ]);
+    assert_eq!(revoked["nmb"], 2);
+
+    // Revoked key is rejected
+    let output = mock.run(&["--api", &new_key, "user", "view", "--id", &id]);
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn prints_keys_as_table() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("bob");
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "user",
+        "keys",
+        "view",
+        "--id",
+        &id.to_string(),
+        "--layout",
+        "table",
+    ]);
+    assert_success(&output);
+    let text = stdout(&output);
+    assert!(text.starts_with("NMB  API_KEY\n"), "{}", text);
+    assert!(text.contains(&key));
+}
+
+#[test]
+fn missing_key_is_not_found() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("carol");
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "user",
+        "keys",
+        "revoke",
+        "--id",
+        &id.to_string(),
+        "--nmb",
+        "7",
+    ]);
+    assert_eq!(output.status.code(), Some(6));
+}
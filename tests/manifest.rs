@@ -0,0 +1,145 @@
+mod common;
+
+use common::{assert_success, stdout, Mock};
+
+static MANIFEST: &str = r#"
+[caches.oak]
+lat = 1.0
+long = 2.0
+descrip = "Oak"
+hint = "Roots"
+
+[caches.elm]
+lat = 3.0
+long = 4.0
+descrip = "Elm"
+hint = "Bark"
+"#;
+
+static CHANGED_MANIFEST: &str = r#"
+[caches.elm]
+lat = 3.0
+long = 4.0
+descrip = "Elm"
+hint = "Hollow"
+
+[caches.pine]
+lat = -5.0
+long = 6.0
+descrip = "Pine"
+hint = "Cone"
+"#;
+
+#[test]
+fn applies_manifest_changes() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    let file = mock.path("caches.toml");
+    let file = file.to_str().unwrap();
+    std::fs::write(file, MANIFEST).unwrap();
+
+    let output = mock.run(&["--api", &key, "cache", "plan", "-f", file]);
+    assert_success(&output);
+    assert!(stdout(&output).contains("Plan: 2 to create, 0 to change, 0 to delete"));
+
+    let output = mock.run_with_stdin(&["--api", &key, "cache", "apply", "-f", file], "yes\n");
+    assert_success(&output);
+    let state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(mock.path("caches.state.json")).unwrap())
+            .unwrap();
+    assert_eq!(state["caches"], serde_json::json!({ "elm": 1, "oak": 2 }));
+
+    std::fs::write(file, CHANGED_MANIFEST).unwrap();
+    let plan = mock.json(&["--api", &key, "cache", "plan", "-f", file]);
+    let actions: Vec<_> = plan
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| (a["action"].as_str().unwrap(), a["name"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        actions,
+        [("change", "elm"), ("delete", "oak"), ("create", "pine")]
+    );
+    assert_eq!(plan[0]["fields"][0]["after"], "Hollow");
+
+    let applied = mock.json(&["--api", &key, "cache", "apply", "-f", file, "--yes"]);
+    assert_eq!(applied["applied"].as_array().unwrap().len(), 3);
+    assert_eq!(
+        mock.json(&["--api", &key, "cache", "view", "--id", "1"])["hint"],
+        "Hollow"
+    );
+    assert_eq!(
+        mock.json(&["--api", &key, "cache", "find"])
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let plan = mock.json(&["--api", &key, "cache", "plan", "-f", file]);
+    assert!(plan.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn apply_requires_confirmation() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("bob");
+    let file = mock.path("caches.toml");
+    let file = file.to_str().unwrap();
+    std::fs::write(file, MANIFEST).unwrap();
+
+    let output = mock.run_with_stdin(&["--api", &key, "cache", "apply", "-f", file], "no\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(mock
+        .json(&["--api", &key, "cache", "find"])
+        .as_array()
+        .unwrap()
+        .is_empty());
+    assert!(!mock.path("caches.state.json").exists());
+}
+
+#[test]
+fn dry_run_prints_request_of_every_action() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("carol");
+    let file = mock.path("caches.toml");
+    let file = file.to_str().unwrap();
+    std::fs::write(file, MANIFEST).unwrap();
+    mock.json(&["--api", &key, "cache", "apply", "-f", file, "--yes"]);
+    let state = std::fs::read_to_string(mock.path("caches.state.json")).unwrap();
+
+    std::fs::write(file, CHANGED_MANIFEST).unwrap();
+    let requests = mock.json(&["--api", &key, "--dry-run", "cache", "apply", "-f", file]);
+    let requests: Vec<_> = requests
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            assert_eq!(r["dry_run"], true);
+            (r["method"].as_str().unwrap(), r["url"].as_str().unwrap())
+        })
+        .collect();
+    let url = |path: &str| format!("{}{}", mock.url(), path);
+    assert_eq!(
+        requests,
+        [
+            ("PUT", url("/cache/1").as_str()),
+            ("DELETE", url("/cache/2").as_str()),
+            ("POST", url("/cache/").as_str()),
+        ]
+    );
+
+    // Nothing is changed on server or in state
+    assert_eq!(
+        mock.json(&["--api", &key, "cache", "view", "--id", "1"])["hint"],
+        "Bark"
+    );
+    assert_eq!(
+        std::fs::read_to_string(mock.path("caches.state.json")).unwrap(),
+        state
+    );
+    let output = mock.run(&["--api", &key, "--dry-run", "cache", "apply", "-f", file]);
+    assert_success(&output);
+    assert!(stdout(&output).contains("Dry run, 3 requests not sent:"));
+}
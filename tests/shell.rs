@@ -0,0 +1,27 @@
+mod common;
+
+use common::{assert_success, Mock};
+
+#[test]
+fn keeps_api_key_out_of_history() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("alice");
+    let history = mock.path("history");
+
+    let output = mock.run_with_stdin(
+        &["shell", "--history-file", history.to_str().unwrap()],
+        &format!("set api {}\nuser view --id 1\n  set   api {}\n", key, key),
+    );
+    assert_success(&output);
+
+    let saved = std::fs::read_to_string(&history).unwrap();
+    assert!(saved.contains("user view --id 1"));
+    assert!(!saved.contains(&key));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&history).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
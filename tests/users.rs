@@ -0,0 +1,90 @@
+mod common;
+
+use common::{assert_success, stdout, Mock};
+
+#[test]
+fn creates_and_views_user() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("alice");
+
+    let user = mock.json(&["--api", &key, "user", "view", "--id", &id.to_string()]);
+    assert_eq!(user["login"], "alice");
+    assert_eq!(user["email"], "alice@example.com");
+
+    let output = mock.run(&["--api", &key, "user", "view", "--id", &id.to_string()]);
+    assert_success(&output);
+    assert!(stdout(&output).contains("alice@example.com"));
+}
+
+#[test]
+fn changes_email() {
+    let mock = Mock::start();
+    let (id, key) = mock.create_user("bob");
+    let id = id.to_string();
+
+    let changed = mock.json(&[
+        "--api",
+        &key,
+        "user",
+        "change",
+        "--id",
+        &id,
+        "--email",
+        "new@example.com",
+    ]);
+    assert_eq!(changed["id"], 1);
+
+    let user = mock.json(&["--api", &key, "user", "view", "--id", &id]);
+    assert_eq!(user["email"], "new@example.com");
+}
+
+#[test]
+fn rejects_change_of_other_user() {
+    let mock = Mock::start();
+    let (_, key) = mock.create_user("carol");
+    let (other, _) = mock.create_user("dave");
+
+    let output = mock.run(&[
+        "--api",
+        &key,
+        "user",
+        "change",
+        "--id",
+        &other.to_string(),
+        "--email",
+        "x@example.com",
+    ]);
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn duplicate_login_is_server_error() {
+    let mock = Mock::start();
+    mock.create_user("erin");
+
+    let output = mock.run_with_stdin(
+        &[
+            "user",
+            "create",
+            "--name",
+            "erin",
+            "--email",
+            "erin@example.com",
+            "--password-stdin",
+        ],
+        "password1\n",
+    );
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn requests_need_api_key() {
+    let mock = Mock::start();
+    mock.create_user("frank");
+
+    let output = mock.run(&["user", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(6));
+
+    let output = mock.run(&["--api", "wrong", "user", "view", "--id", "1"]);
+    assert_eq!(output.status.code(), Some(6));
+}